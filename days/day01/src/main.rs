@@ -1,83 +1,406 @@
 // https://adventofcode.com/2023/day/1
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
 
 fn main() -> Result<(), String> {
 
-    let number_pairs = [
-        ("1", 1), ("one",   1),
-        ("2", 2), ("two",   2),
-        ("3", 3), ("three", 3),
-        ("4", 4), ("four",  4),
-        ("5", 5), ("five",  5),
-        ("6", 6), ("six",   6),
-        ("7", 7), ("seven", 7),
-        ("8", 8), ("eight", 8),
-        ("9", 9), ("nine",  9),
-    ];
+    let options = Options::from_args(std::env::args().skip(1))?;
+    let number_map = load_dictionary(&options.dictionary)?;
 
-    let number_map: NumberMap = number_pairs.into_iter().collect();
+    // day01 --report [file] lists the matches behind each line's calibration value instead
+    if let Some(file) = &options.report {
+        print!("{}", report(&input_from(file)?, &number_map));
+        return Ok(())
+    }
 
     // TODO: parallelize these 4 functions to let the quicker ones display their output first
     let sample1 = input_from("sample-1.txt")?;
     let sample2 = input_from("sample-2.txt")?;
-    println!("Sample part 1: {}", part1(&sample1, &number_map)); // 142
-    println!("Sample part 2: {}", part2(&sample2, &number_map)); // 281
+    println!("Sample part 1: {}", part1(&sample1, &number_map)?); // 142
+    println!("Sample part 2: {}", part2(&sample2, &number_map)?); // 281
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input, &number_map));
-    println!("Part 2: {}", part2(&input, &number_map));
+    println!("Part 1: {}", part1(&input, &number_map)?);
+    println!("Part 2: {}", part2(&input, &number_map)?);
 
     Ok(())
 }
 
-type NumberMap<'a> = HashMap<&'a str, u32>;
+type NumberMap = HashMap<String, u32>;
 
 struct Input {
     lines: Vec<String>
 }
 
-fn part1(input: &Input, number_map: &NumberMap) -> u32 {
+// command line options
+//   --dict <name or file>   english (the default), french, german, spanish, or a dictionary file
+//   --report [file]         print the report for a file (input.txt by default) instead of solving
+struct Options {
+    dictionary: String,
+    report: Option<String>
+}
+
+fn part1(input: &Input, number_map: &NumberMap) -> Result<u32, String> {
 
     // for part 1 we only want the digit digits so create a new map without the word digits
     let number_map: NumberMap =
         number_map.iter()
-                  .filter(|&(key, _)| key.len() == 1)
-                  .map(|(&key, &value)| (key, value))
+                  .filter(|&(key, _)| key.chars().all(|c| c.is_ascii_digit()))
+                  .map(|(key, &value)| (key.clone(), value))
                   .collect();
-    
+
+    let matcher = Matcher::new(&number_map);
+
     // compute and sum the calibration values of each line with the filtered number map
-    input.lines.iter()
-               .map(|line| get_calibration_value(line, &number_map))
-               .sum()
+    sum_calibration_values(input, &matcher)
 }
 
-fn part2(input: &Input, number_map: &NumberMap) -> u32 {
+fn part2(input: &Input, number_map: &NumberMap) -> Result<u32, String> {
+    let matcher = Matcher::new(number_map);
+
     // compute and sum the calibration values of each line with the full number map
+    sum_calibration_values(input, &matcher)
+}
+
+// sum() over Results stops at the first line that doesn't have a number in it
+fn sum_calibration_values(input: &Input, matcher: &Matcher) -> Result<u32, String> {
     input.lines.iter()
-               .map(|line| get_calibration_value(line, number_map))
+               .enumerate()
+               .map(|(i, line)| get_calibration_value(i + 1, line, matcher))
                .sum()
 }
 
-fn get_calibration_value(line: &str, number_map: &NumberMap) -> u32 {
-    let mut numbers_found: Vec<u32> = Vec::new();
+fn get_calibration_value(line_number: usize, line: &str, matcher: &Matcher) -> Result<u32, String> {
+    let first = matcher.first(line);
+    let last  = matcher.last(line);
+
+    match (first, last) {
+        (Some(first), Some(last)) => Ok(first.value*10 + last.value),
+        _ => Err(format!("No digit or number word on line {}: {}", line_number, line))
+    }
+}
+
+// list every line's matched spans and calibration value, marking the first and last matches
+// with a * since they're the ones that count, then call out the lines with zero or one match
+fn report(input: &Input, number_map: &NumberMap) -> String {
+    use std::fmt::Write;
+
+    let matcher = Matcher::new(number_map);
+    let mut out = String::new();
+    let mut suspicious: Vec<(usize, usize)> = vec![];
+
+    for (i, line) in input.lines.iter().enumerate() {
+        let line_number = i + 1;
+
+        let mut matches: Vec<Match> = matcher.find_all(line).collect();
+        matches.sort_by_key(|m| (m.start, Reverse(m.len())));
+
+        let first = matcher.first(line);
+        let last  = matcher.last(line);
+
+        let spans: Vec<String> =
+            matches.iter()
+                   .map(|m| {
+                       let mark = if Some(*m) == first || Some(*m) == last { "*" } else { "" };
+                       format!("{}{}@{}..{}", mark, &line[m.start..m.end], m.start, m.end)
+                   })
+                   .collect();
+
+        let value = match get_calibration_value(line_number, line, &matcher) {
+            Ok(value) => value.to_string(),
+            Err(_)    => "-".to_string()
+        };
+
+        writeln!(out, "{:>5}: {}  =>  {}  [{}]", line_number, line, value, spans.join(" ")).unwrap();
+
+        if matches.len() <= 1 {
+            suspicious.push((line_number, matches.len()));
+        }
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "Lines with zero or one match: {}", suspicious.len()).unwrap();
+
+    for (line_number, count) in suspicious {
+        writeln!(out, "{:>5}: {} match{}  {}",
+                 line_number,
+                 count,
+                 if count == 1 { "" } else { "es" },
+                 input.lines[line_number - 1]).unwrap();
+    }
+
+    out
+}
+
+
+/* Matching */
+
+// a number word or digit found in a line. start and end are byte offsets into the line
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Match {
+    start: usize,
+    end: usize,
+    value: u32
+}
+
+impl Match {
+    fn len(&self) -> usize { self.end - self.start }
+}
+
+// an aho-corasick automaton over bytes. the failure links are folded into a full transition
+// table while building it, so scanning a line is one table lookup per byte
+struct Automaton {
+    // next[state][byte] is the state to go to after reading byte in state
+    next: Vec<[usize; 256]>,
+
+    // the (length, value) of every word ending at each state, including the words reachable
+    // through its failure links. sorted longest first
+    outputs: Vec<Vec<(usize, u32)>>,
+
+    // the length of the longest word, so we know how far past a match we need to look
+    longest: usize
+}
+
+impl Automaton {
+    fn new(words: &[(Vec<u8>, u32)]) -> Self {
+        let mut next: Vec<[usize; 256]> = vec![[0; 256]];
+        let mut outputs: Vec<Vec<(usize, u32)>> = vec![vec![]];
+
+        // build the trie. state 0 is the root and 0 also stands in for "no edge yet", which
+        // is fine since no edge ever leads back into the root while building the trie
+        for (word, value) in words {
+            let mut state = 0;
+            for &byte in word {
+                if next[state][byte as usize] == 0 {
+                    next.push([0; 256]);
+                    outputs.push(vec![]);
+                    next[state][byte as usize] = next.len() - 1;
+                }
+                state = next[state][byte as usize];
+            }
+            outputs[state].push((word.len(), *value));
+        }
+
+        // breadth-first from the root, filling in the missing edges from each state's failure
+        // state and inheriting the failure state's outputs. the root's missing edges already
+        // point back at the root
+        let mut fail: Vec<usize> = vec![0; next.len()];
+        let mut queue: VecDeque<usize> =
+            next[0].iter().copied().filter(|&s| s != 0).collect();
+
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+            outputs[state].sort_by_key(|&(len, _)| Reverse(len));
+
+            let fallback = next[fail[state]];
+
+            for (byte, child) in next[state].iter_mut().enumerate() {
+                if *child != 0 {
+                    fail[*child] = fallback[byte];
+                    queue.push_back(*child);
+                } else {
+                    *child = fallback[byte];
+                }
+            }
+        }
+
+        let longest = words.iter().map(|(word, _)| word.len()).max().unwrap_or(0);
+
+        Automaton { next, outputs, longest }
+    }
+
+    // every match in the bytes, overlaps included, in order of where they end
+    fn matches<I>(&self, bytes: I) -> Matches<'_, I>
+    where I: Iterator<Item=u8>
+    {
+        Matches {
+            automaton: self,
+            bytes,
+            state: 0,
+            position: 0,
+            output: 0
+        }
+    }
+}
+
+struct Matches<'a, I> {
+    automaton: &'a Automaton,
+    bytes: I,
+    state: usize,
 
-    // Iterate over each character index in the string
-    for (i, _) in line.char_indices() {
-        let suffix = &line[i..];
-        
-        for (number, &value) in number_map.iter() {
-            if suffix.starts_with(number) {
-                numbers_found.push(value);
-                break
+    // how many bytes we've read so far
+    position: usize,
+
+    // which of the current state's outputs to report next
+    output: usize
+}
+
+impl<I: Iterator<Item=u8>> Iterator for Matches<'_, I> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let outputs = &self.automaton.outputs[self.state];
+
+            if let Some(&(len, value)) = outputs.get(self.output) {
+                self.output += 1;
+                return Some(Match {
+                    start: self.position - len,
+                    end: self.position,
+                    value
+                })
             }
+
+            let byte = self.bytes.next()?;
+            self.state = self.automaton.next[self.state][byte as usize];
+            self.position += 1;
+            self.output = 0;
         }
     }
+}
 
-    let first = numbers_found.first().unwrap();
-    let last  = numbers_found.last().unwrap();
-    
-    first*10 + last
+// two automatons built once from a NumberMap: one scanning a line from the front to find its
+// first number and one matching the reversed words from the back to find its last number
+struct Matcher {
+    forward: Automaton,
+    backward: Automaton
+}
+
+impl Matcher {
+    fn new(number_map: &NumberMap) -> Self {
+        // sort the words so the automaton doesn't depend on the HashMap's iteration order
+        let mut words: Vec<(Vec<u8>, u32)> =
+            number_map.iter()
+                      .map(|(word, &value)| (word.as_bytes().to_vec(), value))
+                      .collect();
+        words.sort();
+
+        let reversed: Vec<(Vec<u8>, u32)> =
+            words.iter()
+                 .map(|(word, value)| (word.iter().rev().copied().collect(), *value))
+                 .collect();
+
+        Matcher {
+            forward : Automaton::new(&words),
+            backward: Automaton::new(&reversed)
+        }
+    }
+
+    // all the numbers in the line in a single pass, overlaps included, so "twone" gives 2 then 1
+    fn find_all<'a>(&'a self, line: &'a str) -> impl Iterator<Item=Match> + 'a {
+        self.forward.matches(line.bytes())
+    }
+
+    // the match starting earliest in the line, preferring the longer word if two start together
+    fn first(&self, line: &str) -> Option<Match> {
+        let mut best: Option<Match> = None;
+
+        for m in self.find_all(line) {
+            if let Some(b) = best {
+                // matches arrive in order of where they end, so once they end past this point
+                // they can't start at or before the best one we have
+                if m.end > b.start + self.forward.longest { break }
+
+                if (m.start, Reverse(m.len())) >= (b.start, Reverse(b.len())) {
+                    continue
+                }
+            }
+            best = Some(m);
+        }
+
+        best
+    }
+
+    // the match starting latest in the line. scanning the reversed line with the reversed words,
+    // the first match to end is the one that starts last in the original line, and the outputs
+    // are sorted longest first so ties between words starting together go to the longer one
+    fn last(&self, line: &str) -> Option<Match> {
+        let n = line.len();
+
+        self.backward
+            .matches(line.bytes().rev())
+            .next()
+            .map(|m| Match {
+                start: n - m.end,
+                end: n - m.start,
+                value: m.value
+            })
+    }
+}
+
+impl Options {
+    fn from_args(args: impl Iterator<Item=String>) -> Result<Self, String> {
+        let mut options = Options {
+            dictionary: "english".to_string(),
+            report: None
+        };
+
+        // peek so --report can tell whether it was given a file or not
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dict"   => options.dictionary = args.next().ok_or("Expected a dictionary after --dict")?,
+                "--report" => options.report = Some(args.next_if(|a| !a.starts_with("--"))
+                                                        .unwrap_or("input.txt".to_string())),
+                _          => return Err(format!("Unknown argument: {}", arg))
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+// the dictionaries that come with the solution, built into the binary so they work from anywhere
+const BUNDLED_DICTIONARIES: [(&str, &str); 4] = [
+    ("english", include_str!("../dictionaries/english.txt")),
+    ("french",  include_str!("../dictionaries/french.txt")),
+    ("german",  include_str!("../dictionaries/german.txt")),
+    ("spanish", include_str!("../dictionaries/spanish.txt")),
+];
+
+// load one of the bundled dictionaries by name, or else a dictionary file by its path
+fn load_dictionary(name: &str) -> Result<NumberMap, String> {
+    let text = match BUNDLED_DICTIONARIES.iter().find(|(bundled, _)| *bundled == name) {
+        Some((_, text)) => text.to_string(),
+        None            => std::fs::read_to_string(name)
+                               .map_err(|err| format!("Failed to read dictionary {}: {}", name, err))?
+    };
+
+    parse_dictionary(&text)
+}
+
+// one word and its value per line, eg "fünf 5". blank lines and lines starting with # are skipped.
+// the digits themselves are always part of the map so dictionaries only need to list the words
+fn parse_dictionary(text: &str) -> Result<NumberMap, String> {
+    let mut number_map: NumberMap = (1..=9).map(|digit| (digit.to_string(), digit)).collect();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 2 {
+            return Err(format!("Expected a word and a value on dictionary line {}: {}", i + 1, line))
+        }
+
+        // calibration values are two digits, so each word has to stand for a single digit
+        let value: u32 = parts[1].parse()
+                                 .ok()
+                                 .filter(|&value| value <= 9)
+                                 .ok_or(format!("Expected a digit 0-9 on dictionary line {}: {}", i + 1, line))?;
+
+        if let Some(existing) = number_map.insert(parts[0].to_string(), value) {
+            if existing != value {
+                return Err(format!("{} is already {} but dictionary line {} makes it {}",
+                                   parts[0], existing, i + 1, value))
+            }
+        }
+    }
+
+    Ok(number_map)
 }
 
 // Read input from a file
@@ -90,3 +413,157 @@ fn input_from(file: &str) -> Result<Input, String> {
         lines: input.lines().map(|s| s.to_string()).collect::<Vec<String>>()
     })
 }
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number_map() -> NumberMap {
+        load_dictionary("english").unwrap()
+    }
+
+    #[test]
+    fn test_sample_part1() {
+        let input = input_from("sample-1.txt").unwrap();
+        assert_eq!(Ok(142), part1(&input, &number_map()))
+    }
+
+    #[test]
+    fn test_sample_part2() {
+        let input = input_from("sample-2.txt").unwrap();
+        assert_eq!(Ok(281), part2(&input, &number_map()))
+    }
+
+    #[test]
+    fn test_line_without_numbers() {
+        let input = Input {
+            lines: vec!["two1nine".to_string(), "abcxyz".to_string()]
+        };
+
+        assert_eq!(Err("No digit or number word on line 2: abcxyz".to_string()),
+                   part2(&input, &number_map()));
+    }
+
+    #[test]
+    fn test_report() {
+        let input = Input {
+            lines: vec!["xtwone3four".to_string(), "7pqrst".to_string(), "abc".to_string()]
+        };
+        let report = report(&input, &number_map());
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!("    1: xtwone3four  =>  24  [*two@1..4 one@3..6 3@6..7 *four@7..11]", lines[0]);
+        assert_eq!("    2: 7pqrst  =>  77  [*7@0..1]", lines[1]);
+        assert_eq!("    3: abc  =>  -  []", lines[2]);
+        assert_eq!("Lines with zero or one match: 2", lines[4]);
+        assert_eq!("    2: 1 match  7pqrst", lines[5]);
+        assert_eq!("    3: 0 matches  abc", lines[6]);
+    }
+
+    #[test]
+    fn test_overlapping_matches() {
+        let number_map = number_map();
+        let matcher = Matcher::new(&number_map);
+        let values: Vec<u32> = matcher.find_all("twone").map(|m| m.value).collect();
+
+        assert_eq!(vec![2, 1], values);
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        let number_map = number_map();
+        let matcher = Matcher::new(&number_map);
+
+        assert_eq!(Some(Match { start: 1, end: 4, value: 1 }), matcher.first("zoneight234"));
+        assert_eq!(Some(Match { start: 10, end: 11, value: 4 }), matcher.last("zoneight234"));
+        assert_eq!(Some(Match { start: 3, end: 6, value: 1 }), matcher.last("xtwone"));
+        assert_eq!(None, matcher.first("abc"));
+    }
+
+    #[test]
+    fn test_first_prefers_earliest_start() {
+        // "eve" is found first since it ends first, but "seven" starts earlier
+        let number_map = parse_dictionary("seven 7\neve 3").unwrap();
+        let matcher = Matcher::new(&number_map);
+
+        assert_eq!(7, matcher.first("sevenx").unwrap().value);
+        assert_eq!(3, matcher.last("sevenx").unwrap().value);
+    }
+
+    #[test]
+    fn test_first_prefers_longer_word() {
+        // "se" is a prefix of "seven" so it's found first, but the longer word should win
+        let number_map = parse_dictionary("se 1\nseven 7").unwrap();
+        let matcher = Matcher::new(&number_map);
+
+        assert_eq!(7, matcher.first("seven").unwrap().value);
+        assert_eq!(7, matcher.last("seven").unwrap().value);
+        assert_eq!(1, matcher.first("sevex").unwrap().value);
+    }
+
+    #[test]
+    fn test_part1_ignores_short_words() {
+        // one-letter roman numerals aren't digits, so part 1 shouldn't pick them up
+        let number_map = parse_dictionary("i 1\nv 5").unwrap();
+        let input = Input { lines: vec!["v3i7v".to_string()] };
+
+        assert_eq!(Ok(37), part1(&input, &number_map));
+        assert_eq!(Ok(55), part2(&input, &number_map));
+    }
+
+    #[test]
+    fn test_bundled_dictionaries() {
+        let lines = |lines: &[&str]| Input {
+            lines: lines.iter().map(|line| line.to_string()).collect()
+        };
+
+        let french  = lines(&["xdeuxtroisquatre", "huitneuf"]);
+        let german  = lines(&["zweifünf", "achtxeins"]);
+        let spanish = lines(&["unodos", "9ocho"]);
+
+        assert_eq!(Ok(24 + 89), part2(&french,  &load_dictionary("french").unwrap()));
+        assert_eq!(Ok(25 + 81), part2(&german,  &load_dictionary("german").unwrap()));
+        assert_eq!(Ok(12 + 98), part2(&spanish, &load_dictionary("spanish").unwrap()));
+    }
+
+    #[test]
+    fn test_multibyte_words() {
+        let number_map = load_dictionary("german").unwrap();
+        let matcher = Matcher::new(&number_map);
+        let line = "äfünfßvierö";
+
+        let first = matcher.first(line).unwrap();
+        let last  = matcher.last(line).unwrap();
+
+        assert_eq!("fünf", &line[first.start..first.end]);
+        assert_eq!("vier", &line[last.start..last.end]);
+        assert_eq!(Ok(54), get_calibration_value(1, line, &matcher));
+    }
+
+    #[test]
+    fn test_parse_dictionary_errors() {
+        assert!(parse_dictionary("eleven 11").is_err());
+        assert!(parse_dictionary("one").is_err());
+        assert!(parse_dictionary("one 1\none 2").is_err());
+        assert!(parse_dictionary("# comment\n\none 1\none 1").is_ok());
+    }
+
+    #[test]
+    fn test_options() {
+        let args = |args: &[&str]| Options::from_args(args.iter().map(|arg| arg.to_string()));
+
+        let options = args(&["--report", "--dict", "french"]).unwrap();
+        assert_eq!("french", options.dictionary);
+        assert_eq!(Some("input.txt".to_string()), options.report);
+
+        let options = args(&["--report", "sample-2.txt"]).unwrap();
+        assert_eq!("english", options.dictionary);
+        assert_eq!(Some("sample-2.txt".to_string()), options.report);
+
+        assert!(args(&["--dict"]).is_err());
+        assert!(args(&["--bogus"]).is_err());
+    }
+}
@@ -1,16 +1,26 @@
 // https://adventofcode.com/2023/day/2
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 
 fn main() -> Result<(), String> {
 
+    let options = Options::from_args(std::env::args().skip(1))?;
+    let bag = &options.bag;
+
+    // day02 --explain [file] shows how each game was judged instead of just the totals
+    if let Some(file) = &options.explain {
+        print!("{}", explain(&input_from(file)?, bag));
+        return Ok(())
+    }
+
     // TODO: parallelize these 4 functions to let the quicker ones display their output first
     let sample = input_from("sample.txt")?;
-    println!("Sample part 1: {}", part1(&sample));
+    println!("Sample part 1: {}", part1(&sample, bag));
     println!("Sample part 2: {}", part2(&sample));
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input));
+    println!("Part 1: {}", part1(&input, bag));
     println!("Part 2: {}", part2(&input));
 
     Ok(())
@@ -25,30 +35,115 @@ struct Game {
     handfuls: Vec<Handful>,
 }
 
+// counts of cubes keyed by color name. a BTreeMap so colors always come out in the same order
+type Cubes = BTreeMap<String, u32>;
+
 #[derive(Default)]   // derive Default because we'll be using a mutable Handful instance
-struct Handful {     // in part 2 and we need to start off with zeroes
-    red: u32,
-    green: u32,
-    blue: u32,
+struct Handful {     // in part 2 and we need to start off with no cubes
+    cubes: Cubes,
+
+    // the colors in the order they were first mentioned, since the map sorts them by name
+    order: Vec<String>
+}
+
+// the most cubes of each color the elf could have in the bag. colors that aren't listed
+// aren't in the bag at all
+struct Bag {
+    cubes: Cubes
+}
+
+// maximums defined in the problem description
+const DEFAULT_BAG: &str = "red=12,green=13,blue=14";
+
+// command line options
+//   --bag red=12,green=13,blue=14,yellow=3   the bag to check the games against in part 1
+//   --bag-file <file>                        the same but read from a file, one color per line is ok
+//   --explain [file]                         explain each game in a file (input.txt by default)
+struct Options {
+    bag: Bag,
+    explain: Option<String>
+}
+
+// the first time a game showed more cubes of a color than the bag holds
+#[derive(Debug, PartialEq)]
+struct Overflow {
+    handful: usize,     // numbered from 1 like the games
+    color: String,
+    count: u32,
+    limit: u32
+}
+
+impl Handful {
+    // how many cubes of this color were shown. colors not mentioned count as zero
+    fn count(&self, color: &str) -> u32 {
+        self.cubes.get(color).copied().unwrap_or(0)
+    }
+}
+
+impl Bag {
+    fn limit(&self, color: &str) -> u32 {
+        self.cubes.get(color).copied().unwrap_or(0)
+    }
+}
+
+impl Default for Bag {
+    fn default() -> Self {
+        DEFAULT_BAG.parse().expect("DEFAULT_BAG should parse")
+    }
+}
+
+impl Game {
+    // find the first handful, and the first color within it, that couldn't have come out of the
+    // bag. None means the game is possible
+    fn first_overflow(&self, bag: &Bag) -> Option<Overflow> {
+        self.handfuls.iter()
+                     .enumerate()
+                     .find_map(|(i, handful)| {
+                         handful.order.iter()
+                                      .map(|color| (color, handful.count(color)))
+                                      .find(|&(color, count)| count > bag.limit(color))
+                                      .map(|(color, count)| Overflow {
+                                          handful: i + 1,
+                                          color: color.clone(),
+                                          count,
+                                          limit: bag.limit(color)
+                                      })
+                     })
+    }
+
+    // the fewest cubes of each color that could have been in the bag for this game
+    fn minimum_bag(&self) -> Handful {
+        let mut handful = Handful::default();
+        for h in self.handfuls.iter() {
+            for (color, &count) in h.cubes.iter() {
+                if !handful.cubes.contains_key(color) {
+                    handful.order.push(color.clone());
+                }
+                let most = handful.cubes.entry(color.clone()).or_insert(0);
+                *most = (*most).max(count);
+            }
+        }
+        handful
+    }
 }
 
-fn part1(input: &Input) -> u32 {
-    
-    // maximums defined in the problem description
-    const CUBES_RED  : u32 = 12;
-    const CUBES_GREEN: u32 = 13;
-    const CUBES_BLUE : u32 = 14;
+impl Input {
+    // every color mentioned anywhere in the games
+    fn colors(&self) -> BTreeSet<&str> {
+        self.games.iter()
+                  .flat_map(|game| game.handfuls.iter())
+                  .flat_map(|handful| handful.cubes.keys())
+                  .map(|color| color.as_str())
+                  .collect()
+    }
+}
 
+fn part1(input: &Input, bag: &Bag) -> u32 {
     let game_ids =
         input.games.iter()
                    .filter(|game| {
-                        // keep only the games whose handfuls are all less than the maximums
-                        game.handfuls.iter().all(
-                            |handful| {
-                                   handful.red <= CUBES_RED
-                                && handful.green <= CUBES_GREEN
-                                && handful.blue <= CUBES_BLUE
-                            })
+                        // keep only the games whose handfuls all fit in the bag
+                        game.first_overflow(bag).is_none()
                    })
                    .map(|game| game.game_id);
 
@@ -58,18 +153,71 @@ fn part1(input: &Input) -> u32 {
 fn part2(input: &Input) -> u32 {
     let mut powers: Vec<u32> = Vec::new();
 
+    // a game that never shows a color that other games do needs zero of it, so its power is zero
+    let colors = input.colors();
+
     for game in &input.games {
-        let mut handful = Handful::default();
-        for h in game.handfuls.iter() {
-            handful.red   = handful.red.max(h.red);
-            handful.green = handful.green.max(h.green);
-            handful.blue  = handful.blue.max(h.blue);
-        }
-        powers.push(handful.red * handful.green * handful.blue)
+        let handful = game.minimum_bag();
+        powers.push(power(&handful, &colors))
     }
     powers.iter().sum::<u32>()
 }
 
+// a table with a row per game showing whether it was possible with this bag (and if not, why
+// not) along with the minimum bag from part 2 and its power, then the totals for both parts
+fn explain(input: &Input, bag: &Bag) -> String {
+    use std::fmt::Write;
+
+    let colors = input.colors();
+
+    // the minimum bags vary in width so line up the columns after them
+    let minimums: Vec<String> =
+        input.games.iter()
+                   .map(|game| {
+                       let handful = game.minimum_bag();
+                       colors.iter()
+                             .map(|color| format!("{}={}", color, handful.count(color)))
+                             .collect::<Vec<String>>()
+                             .join(" ")
+                   })
+                   .collect();
+
+    let width = minimums.iter()
+                        .map(|minimum| minimum.len())
+                        .chain(std::iter::once("Minimum bag".len()))
+                        .max()
+                        .unwrap();
+
+    let mut out = String::new();
+    writeln!(out, "{:>5}  {:<8}  {:<width$}  {:>7}  Reason", "Game", "Possible", "Minimum bag", "Power").unwrap();
+
+    for (game, minimum) in input.games.iter().zip(minimums) {
+        let power = power(&game.minimum_bag(), &colors);
+
+        let (possible, reason) = match game.first_overflow(bag) {
+            None    => ("yes", String::new()),
+            Some(o) => ("no", format!("handful {}: {} {} > {}", o.handful, o.count, o.color, o.limit))
+        };
+
+        writeln!(out, "{:>5}  {:<8}  {:<width$}  {:>7}  {}",
+                 game.game_id, possible, minimum, power, reason).unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "Part 1: {}", part1(input, bag)).unwrap();
+    writeln!(out, "Part 2: {}", part2(input)).unwrap();
+
+    // the table has trailing spaces on the possible games that have no reason
+    out.lines().map(|line| line.trim_end().to_string() + "\n").collect()
+}
+
+// the numbers of cubes of each color multiplied together
+fn power(handful: &Handful, colors: &BTreeSet<&str>) -> u32 {
+    colors.iter()
+          .map(|color| handful.count(color))
+          .product()
+}
+
 
 /*  Parsing  */
 
@@ -79,9 +227,8 @@ impl FromStr for Handful {
 
     // "3 blue, 4 red, 2 green"
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut red = 0;
-        let mut green = 0;
-        let mut blue = 0;
+        let mut cubes = Cubes::new();
+        let mut order = Vec::new();
 
         for pair in s.split(',') {
             let parts: Vec<&str> = pair.split_whitespace().collect();
@@ -89,15 +236,69 @@ impl FromStr for Handful {
                 return Err(format!("Invalid format: {}", pair));
             }
             let count: u32 = parts[0].parse().map_err(|_| format!("Invalid number: {}", parts[0]))?;
-            match parts[1] {
-                "red" => red += count,
-                "green" => green += count,
-                "blue" => blue += count,
-                _ => return Err(format!("Invalid color: {}", parts[1])),
+            if !cubes.contains_key(parts[1]) {
+                order.push(parts[1].to_string());
+            }
+            *cubes.entry(parts[1].to_string()).or_insert(0) += count;
+        }
+
+        Ok(Handful { cubes, order })
+    }
+}
+
+impl FromStr for Bag {
+    type Err = String;
+
+    // "red=12,green=13,blue=14", or the same split over lines when it comes from a file
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cubes = Cubes::new();
+
+        for pair in s.split([',', '\n']).map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (color, count) = pair.split_once('=')
+                                     .ok_or(format!("Expected color=count in bag: {}", pair))?;
+
+            let count: u32 = count.trim().parse().map_err(|_| format!("Invalid number in bag: {}", pair))?;
+
+            if cubes.insert(color.trim().to_string(), count).is_some() {
+                return Err(format!("Color listed twice in bag: {}", color.trim()))
+            }
+        }
+
+        Ok(Bag { cubes })
+    }
+}
+
+impl Options {
+    fn from_args(args: impl Iterator<Item=String>) -> Result<Self, String> {
+        let mut options = Options {
+            bag: Bag::default(),
+            explain: None
+        };
+
+        // peek so --explain can tell whether it was given a file or not
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bag" => {
+                    let spec = args.next().ok_or("Expected a bag after --bag")?;
+                    options.bag = spec.parse()?;
+                },
+                "--bag-file" => {
+                    let file = args.next().ok_or("Expected a file after --bag-file")?;
+                    options.bag = std::fs::read_to_string(&file)
+                                      .map_err(|err| format!("Failed to read bag file {}: {}", file, err))?
+                                      .parse()?;
+                },
+                "--explain" => {
+                    options.explain = Some(args.next_if(|a| !a.starts_with("--"))
+                                               .unwrap_or("input.txt".to_string()));
+                },
+                _ => return Err(format!("Unknown argument: {}", arg))
             }
         }
 
-        Ok(Handful { red, green, blue })
+        Ok(options)
     }
 }
 
@@ -152,3 +353,91 @@ fn input_from(file: &str) -> Result<Input, String> {
 
     Ok(Input { games })
 }
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_part1() {
+        let input = input_from("sample.txt").unwrap();
+        assert_eq!(8, part1(&input, &Bag::default()))
+    }
+
+    #[test]
+    fn test_sample_part2() {
+        let input = input_from("sample.txt").unwrap();
+        assert_eq!(2286, part2(&input))
+    }
+
+    #[test]
+    fn test_what_if_bags() {
+        let input = input_from("sample.txt").unwrap();
+
+        // enough of everything lets every game through, and no green at all blocks every game
+        let big: Bag = "red=100,green=100,blue=100".parse().unwrap();
+        let no_green: Bag = "red=100,blue=100".parse().unwrap();
+        assert_eq!(1 + 2 + 3 + 4 + 5, part1(&input, &big));
+        assert_eq!(0, part1(&input, &no_green));
+    }
+
+    #[test]
+    fn test_other_colors() {
+        let input = Input {
+            games: vec!["Game 1: 3 yellow, 4 red; 1 red".parse().unwrap(),
+                        "Game 2: 1 red, 2 green".parse().unwrap()]
+        };
+
+        let bag: Bag = "red=12,green=13,blue=14,yellow=3".parse().unwrap();
+        assert_eq!(3, part1(&input, &bag));
+        assert_eq!(2, part1(&input, &Bag::default()));
+
+        // game 1 has no green and game 2 has no yellow
+        assert_eq!(0, part2(&input));
+    }
+
+    #[test]
+    fn test_parse_bag() {
+        let bag: Bag = "red=12\n green = 13 \n".parse().unwrap();
+        assert_eq!(12, bag.limit("red"));
+        assert_eq!(13, bag.limit("green"));
+        assert_eq!(0, bag.limit("blue"));
+
+        assert!("red".parse::<Bag>().is_err());
+        assert!("red=x".parse::<Bag>().is_err());
+        assert!("red=1,red=2".parse::<Bag>().is_err());
+    }
+
+    #[test]
+    fn test_first_overflow() {
+        let input = input_from("sample.txt").unwrap();
+        let bag = Bag::default();
+
+        assert_eq!(None, input.games[0].first_overflow(&bag));
+        assert_eq!(Some(Overflow { handful: 1, color: "red".to_string(), count: 20, limit: 12 }),
+                   input.games[2].first_overflow(&bag));
+        assert_eq!(Some(Overflow { handful: 3, color: "blue".to_string(), count: 15, limit: 14 }),
+                   input.games[3].first_overflow(&bag));
+
+        // both colors are over, and red comes first in the game even though blue sorts first
+        let game = "Game 1: 20 red, 15 blue".parse::<Game>().unwrap();
+        assert_eq!(Some(Overflow { handful: 1, color: "red".to_string(), count: 20, limit: 12 }),
+                   game.first_overflow(&bag));
+    }
+
+    #[test]
+    fn test_explain() {
+        let input = input_from("sample.txt").unwrap();
+        let explained = explain(&input, &Bag::default());
+        let lines: Vec<&str> = explained.lines().collect();
+
+        assert_eq!(" Game  Possible  Minimum bag               Power  Reason", lines[0]);
+        assert_eq!("    1  yes       blue=6 green=2 red=4         48", lines[1]);
+        assert_eq!("    3  no        blue=6 green=13 red=20     1560  handful 1: 20 red > 12", lines[3]);
+        assert_eq!("Part 1: 8", lines[7]);
+        assert_eq!("Part 2: 2286", lines[8]);
+    }
+}
@@ -1,15 +1,37 @@
 // https://adventofcode.com/2023/day/3
 
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
-use regex::Regex;
 
 fn main() -> Result<(), String> {
 
+    // day03 --inspect [file] counts the symbols and ignored characters instead of solving
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--inspect") {
+        let file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+        print!("{}", inspect(&input_from(file)?));
+        return Ok(())
+    }
+
+    // day03 --gears <symbol> <count> <sum|product|max> [file] runs a gear query on a schematic,
+    // eg part 2 is the same as --gears '*' 2 product
+    if args.get(1).map(String::as_str) == Some("--gears") {
+        if args.len() < 5 {
+            return Err("Usage: --gears <symbol> <count> <sum|product|max> [file]".into())
+        }
+        let query = GearQuery::from_args(&args[2], &args[3], &args[4])?;
+        let file = args.get(5).map(String::as_str).unwrap_or("input.txt");
+        print!("{}", describe_gears(&input_from(file)?, &query));
+        return Ok(())
+    }
+
     let sample = input_from("sample.txt")?;
+    warn_about_ignored(&sample, "sample.txt");
     println!("Sample part 1: {}", part1(&sample)); // 4361
     println!("Sample part 2: {}", part2(&sample)); // 467835
 
     let input = input_from("input.txt")?;
+    warn_about_ignored(&input, "input.txt");
     println!("Part 1: {}", part1(&input));
     println!("Part 2: {}", part2(&input));
 
@@ -19,6 +41,14 @@ fn main() -> Result<(), String> {
 struct Input {
     numbers: Vec<Number>,
     symbols: Vec<Symbol>,
+
+    // spatial indexes from each cell to whatever occupies it. a number covers one cell per digit.
+    // the values are indices into the numbers and symbols vectors
+    number_at: HashMap<Position, usize>,
+    symbol_at: HashMap<Position, usize>,
+
+    // characters that were neither numbers, dots, nor symbols
+    ignored: Vec<(Position, char)>,
 }
 
 struct Number {
@@ -32,29 +62,39 @@ struct Symbol {
     symbol: char
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Eq, Hash, PartialEq)]
 struct Position {
     row: usize,
     col: usize
 }
 
-fn part1(input: &Input) -> u32 {
+// a symbol along with the numbers touching it
+struct Gear<'a> {
+    symbol: &'a Symbol,
+    numbers: Vec<&'a Number>
+}
 
-    // first gather the coordinates of all the symbols
-    let symbols_at: Vec<&Position> =
-        input.symbols.iter()
-                     .map(|symbol| &symbol.position)
-                     .collect();
+// how to combine the numbers around a gear into a single value
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Aggregate {
+    Sum,
+    Product,
+    Max
+}
+
+// which symbols act as gears, how many numbers they need to touch, and how to combine them
+struct GearQuery {
+    symbol: char,
+    count: usize,
+    aggregate: Aggregate
+}
+
+fn part1(input: &Input) -> u32 {
 
     // find the numbers that have a symbol somewhere in their neighbourhood
     let part_numbers: Vec<&Number> =
         input.numbers.iter()
-                     .filter(|&number| {
-                        let nd = get_neighbourhood_of_number(number);
-
-                        // look for intersections of the neighbourhood and symbol positions
-                        return nd.iter().any(|pos| symbols_at.contains(&pos))
-                     })
+                     .filter(|&number| !input.neighbours_of(number).is_empty())
                      .collect();
 
     part_numbers.iter()
@@ -62,28 +102,180 @@ fn part1(input: &Input) -> u32 {
                 .sum()
 }
 
-fn part2(input: &Input) -> u32 {
-    
-    let gears =
-        input.symbols.iter()
-                     .filter(|symbol| symbol.symbol == '*')
-                     .map(|symbol| get_numbers_around_position(&symbol.position, &input.numbers))
-                     .filter(|numbers| numbers.len() == 2);
-    
+fn part2(input: &Input) -> u64 {
+
     // calculate the sum of the gear ratios (actually products)
-    gears.map(|pair| pair[0].value * pair[1].value)
-         .sum()
-}
-
-fn get_numbers_around_position<'a>(position: &Position, numbers: &'a [Number]) -> Vec<&'a Number> {
-    numbers.iter()
-           .filter(|&number| {
-                // look for intersections of this number's neighbourhood and the symbol's position
-                get_neighbourhood_of_number(number)
-                    .iter()
-                    .any(|pos| pos == position)
-           })
+    let query = GearQuery {
+        symbol: '*',
+        count: 2,
+        aggregate: Aggregate::Product
+    };
+
+    query.total(input)
+}
+
+impl GearQuery {
+    fn from_args(symbol: &str, count: &str, aggregate: &str) -> Result<Self, String> {
+        let mut chars = symbol.chars();
+        let symbol = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _               => return Err(format!("Expected a single symbol character: {}", symbol))
+        };
+
+        Ok(GearQuery {
+            symbol,
+            count: count.parse().map_err(|_| format!("Invalid count: {}", count))?,
+            aggregate: aggregate.parse()?
+        })
+    }
+
+    // the value of each matching gear added up
+    fn total(&self, input: &Input) -> u64 {
+        input.gears(self.symbol, self.count)
+             .iter()
+             .map(|gear| self.aggregate.apply(&gear.numbers))
+             .sum()
+    }
+}
+
+impl Aggregate {
+    fn apply(&self, numbers: &[&Number]) -> u64 {
+        let values = numbers.iter().map(|number| number.value as u64);
+
+        match self {
+            Aggregate::Sum     => values.sum(),
+            Aggregate::Product => values.product(),
+            Aggregate::Max     => values.max().unwrap_or(0)
+        }
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum"     => Ok(Aggregate::Sum),
+            "product" => Ok(Aggregate::Product),
+            "max"     => Ok(Aggregate::Max),
+            _         => Err(format!("Unknown aggregate, expected sum, product or max: {}", s))
+        }
+    }
+}
+
+// one line per matching gear with its numbers and value, then the total
+fn describe_gears(input: &Input, query: &GearQuery) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+
+    for gear in input.gears(query.symbol, query.count) {
+        let values: Vec<String> = gear.numbers.iter().map(|n| n.value.to_string()).collect();
+
+        writeln!(out, "'{}' at row {}, col {}: {} => {}",
+                 gear.symbol.symbol,
+                 gear.symbol.position.row + 1,
+                 gear.symbol.position.col + 1,
+                 values.join(", "),
+                 query.aggregate.apply(&gear.numbers)).unwrap();
+    }
+
+    writeln!(out, "Total: {}", query.total(input)).unwrap();
+    out
+}
+
+impl Input {
+    // the symbols touching this number, including diagonally
+    fn neighbours_of(&self, number: &Number) -> Vec<&Symbol> {
+        get_neighbourhood_of_number(number)
+            .iter()
+            .filter_map(|pos| self.symbol_at.get(pos))
+            .map(|&i| &self.symbols[i])
+            .collect()
+    }
+
+    // the distinct numbers touching this symbol. a number can touch it with more than one of its
+    // digits so only keep the first time we see each one
+    fn numbers_around(&self, symbol: &Symbol) -> Vec<&Number> {
+        let mut ids: Vec<usize> = vec![];
+
+        for pos in get_neighbourhood_of_position(&symbol.position) {
+            if let Some(&id) = self.number_at.get(&pos) {
+                if !ids.contains(&id) { ids.push(id) }
+            }
+        }
+
+        ids.into_iter()
+           .map(|id| &self.numbers[id])
            .collect()
+    }
+
+    // every occurrence of this symbol that touches exactly count numbers, in reading order
+    fn gears(&self, symbol: char, count: usize) -> Vec<Gear<'_>> {
+        self.symbols.iter()
+                    .filter(|s| s.symbol == symbol)
+                    .map(|s| Gear {
+                        symbol: s,
+                        numbers: self.numbers_around(s)
+                    })
+                    .filter(|gear| gear.numbers.len() == count)
+                    .collect()
+    }
+}
+
+impl Input {
+    // how many times each symbol character shows up in the schematic
+    fn symbol_histogram(&self) -> BTreeMap<char, usize> {
+        let mut histogram = BTreeMap::new();
+        for symbol in &self.symbols {
+            *histogram.entry(symbol.symbol).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+// list the symbols found with their counts, then any characters that were skipped and where
+// the first of each one was, so a surprising answer can be traced back to the input
+fn inspect(input: &Input) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+
+    writeln!(out, "Symbols:").unwrap();
+    for (symbol, count) in input.symbol_histogram() {
+        writeln!(out, "  '{}' occurs {} times", symbol, count).unwrap();
+    }
+
+    let mut ignored: BTreeMap<char, (usize, &Position)> = BTreeMap::new();
+    for (position, c) in &input.ignored {
+        ignored.entry(*c).or_insert((0, position)).0 += 1;
+    }
+
+    if !ignored.is_empty() {
+        writeln!(out, "Ignored:").unwrap();
+        for (c, (count, first)) in ignored {
+            writeln!(out, "  {:?} occurs {} times, first at row {}, col {}",
+                     c, count, first.row + 1, first.col + 1).unwrap();
+        }
+    }
+
+    out
+}
+
+fn warn_about_ignored(input: &Input, file: &str) {
+    if !input.ignored.is_empty() {
+        eprintln!("Warning: ignored {} characters in {}, run with --inspect {} to see them",
+                  input.ignored.len(), file, file)
+    }
+}
+
+// the 3x3 block of cells centred on a position, clipped at the top and left edges
+fn get_neighbourhood_of_position(position: &Position) -> Vec<Position> {
+    get_neighbourhood_of_number(&Number {
+        position: position.clone(),
+        value: 0,
+        length: 1
+    })
 }
 
 // chatgpt 4.0
@@ -116,50 +308,90 @@ impl FromStr for Input {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut numbers: Vec<Number> = Vec::new();
         let mut symbols: Vec<Symbol> = Vec::new();
+        let mut ignored: Vec<(Position, char)> = Vec::new();
         
         for (row, line) in s.lines().enumerate() {
-            let (mut nums, mut syms) = line_to_gridthings(line, row);
+            let (mut nums, mut syms, mut igns) = line_to_gridthings(line, row)?;
             numbers.append(&mut nums);
             symbols.append(&mut syms);
+            ignored.append(&mut igns);
         }
 
+        let number_at: HashMap<Position, usize> =
+            numbers.iter()
+                   .enumerate()
+                   .flat_map(|(id, number)| {
+                       let Position { row, col } = number.position;
+                       (col..col + number.length).map(move |col| (Position { row, col }, id))
+                   })
+                   .collect();
+
+        let symbol_at: HashMap<Position, usize> =
+            symbols.iter()
+                   .enumerate()
+                   .map(|(id, symbol)| (symbol.position.clone(), id))
+                   .collect();
+
         Ok(Input { 
             numbers,
-            symbols
+            symbols,
+            number_at,
+            symbol_at,
+            ignored
         })
     }
 }
 
-fn line_to_gridthings(line: &str, row: usize) -> (Vec<Number>, Vec<Symbol>) {
+// the pieces of one line of the schematic: numbers, symbols, and the characters we skipped
+type Gridthings = (Vec<Number>, Vec<Symbol>, Vec<(Position, char)>);
+
+// runs of digits are numbers, dots are empty space, and anything else that's visible is a symbol.
+// the symbols used to come from a hand-typed list that was missing the &, which quietly made the
+// part 2 answer too low, so now we take whatever the input gives us. whitespace and control
+// characters (eg a stray \r) aren't symbols, we just note them so they can be reported
+fn line_to_gridthings(line: &str, row: usize) -> Result<Gridthings, String> {
     let mut numbers: Vec<Number> = Vec::new();
     let mut symbols: Vec<Symbol> = Vec::new();
+    let mut ignored: Vec<(Position, char)> = Vec::new();
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut col = 0;
 
-    // match a string of digits or any of these cool symbols
-    let re = Regex::new(r"(\d+|[#$&%*+/=@-])").unwrap();
+    while col < chars.len() {
+        let c = chars[col];
 
-    for mat in re.find_iter(line) {
-        let value = &line[mat.start()..mat.end()]; // Extract the matched string
-        let col = mat.start(); // Get the offset
+        if c.is_ascii_digit() {
+            // gather up the rest of the digits in this number
+            let length = chars[col..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let digits: String = chars[col..col + length].iter().collect();
 
-        // if it starts with a digit it's a number
-        if value.chars().next().unwrap().is_ascii_digit() {
             numbers.push(Number {
                 position: Position { row, col },
-                value: value.parse().unwrap(),
-                length: value.len()
-            })
-        } 
-        // otherwise it's a symbol
-        else
-        {
+                value: digits.parse().map_err(|_| format!("Number too big at row {}, col {}: {}", row + 1, col + 1, digits))?,
+                length
+            });
+
+            col += length;
+            continue
+        }
+
+        if c == '.' {
+            // empty space
+        }
+        else if c.is_whitespace() || c.is_control() {
+            ignored.push((Position { row, col }, c))
+        }
+        else {
             symbols.push(Symbol {
                 position: Position { row, col },
-                symbol: value.chars().next().unwrap()
+                symbol: c
             })
         }
+
+        col += 1;
     }
 
-    (numbers, symbols)
+    Ok((numbers, symbols, ignored))
 }
 
 // Read input from a file
@@ -190,6 +422,67 @@ mod tests {
         assert_eq!(467835, part2(&input))
     }
 
+    #[test]
+    fn test_numbers_around() {
+        let input = input_from("sample.txt").unwrap();
+
+        // the * below 467 also touches 35, and the 633 touches the # below it
+        let values: Vec<u32> = input.numbers_around(&input.symbols[0]).iter().map(|n| n.value).collect();
+        assert_eq!(vec![467, 35], values);
+
+        let symbols: Vec<char> = input.neighbours_of(&input.numbers[3]).iter().map(|s| s.symbol).collect();
+        assert_eq!(vec!['#'], symbols);
+
+        assert!(input.neighbours_of(&input.numbers[1]).is_empty());
+    }
+
+    #[test]
+    fn test_gear_queries() {
+        let input = input_from("sample.txt").unwrap();
+
+        // the * in the middle of the sample only touches 617
+        assert_eq!(1, input.gears('*', 1).len());
+        assert_eq!(2, input.gears('*', 2).len());
+        assert!(input.gears('#', 2).is_empty());
+
+        let query = GearQuery::from_args("*", "2", "sum").unwrap();
+        assert_eq!(467 + 35 + 755 + 598, query.total(&input));
+
+        let query = GearQuery::from_args("*", "2", "max").unwrap();
+        assert_eq!(467 + 755, query.total(&input));
+
+        let query = GearQuery::from_args("+", "1", "product").unwrap();
+        assert_eq!(592, query.total(&input));
+
+        assert!(GearQuery::from_args("**", "2", "sum").is_err());
+        assert!(GearQuery::from_args("*", "two", "sum").is_err());
+        assert!(GearQuery::from_args("*", "2", "mean").is_err());
+    }
+
+    #[test]
+    fn test_describe_gears() {
+        let input = input_from("sample.txt").unwrap();
+        let query = GearQuery::from_args("*", "2", "product").unwrap();
+
+        assert_eq!("'*' at row 2, col 4: 467, 35 => 16345\n\
+                    '*' at row 9, col 6: 755, 598 => 451490\n\
+                    Total: 467835\n",
+                   describe_gears(&input, &query));
+    }
+
+    #[test]
+    fn test_inspect() {
+        let input = "12&.\r\n.$ .\n".parse::<Input>().unwrap();
+
+        assert_eq!(vec![('$', 1), ('&', 1)], input.symbol_histogram().into_iter().collect::<Vec<_>>());
+        assert_eq!("Symbols:\n  '$' occurs 1 times\n  '&' occurs 1 times\nIgnored:\n  ' ' occurs 1 times, first at row 2, col 3\n",
+                   inspect(&input));
+
+        // positions in errors count from 1 like the rest of the reports
+        assert_eq!(Some("Number too big at row 2, col 3: 99999999999".to_string()),
+                   "...\n..99999999999".parse::<Input>().err());
+    }
+
     #[test]
     fn test_part1() {
         let input = input_from("input.txt").unwrap();
@@ -1,18 +1,38 @@
 // https://adventofcode.com/2023/day/4
 
+use std::ops::Range;
 use std::str::FromStr;
 use std::num::ParseIntError;
 
 fn main() -> Result<(), String> {
 
+    // day04 --trace <csv|dot> [file] writes out how the piles grew in part 2 instead of solving
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--trace") {
+        let format = args.get(2).ok_or("Expected csv or dot after --trace")?;
+        let file = args.get(3).map(String::as_str).unwrap_or("input.txt");
+        let cascade = cascade(&input_from(file)?);
+
+        match format.as_str() {
+            "csv" => print!("{}", trace_csv(&cascade)),
+            "dot" => print!("{}", trace_dot(&cascade)),
+            _     => return Err(format!("Unknown trace format, expected csv or dot: {}", format))
+        }
+        return Ok(())
+    }
+
     let sample = input_from("sample.txt")?;
-    println!("Sample part 1: {}", part1(&sample)); // 13
+    println!("Sample part 1: {}", part1(&sample)?); // 13
     println!("Sample part 2: {}", part2(&sample)); // 30
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input));
+    println!("Part 1: {}", part1(&input)?);
     println!("Part 2: {}", part2(&input));
 
+    for i in cascade(&input).overflows {
+        eprintln!("Warning: card {} won copies of cards past the end of the table", i + 1)
+    }
+
     Ok(())
 }
 
@@ -21,49 +41,153 @@ struct Input {
 }
 
 struct Card {
-    winning_numbers: Vec<u32>,
-    my_numbers: Vec<u32>
+    winning_numbers: NumberSet,
+    my_numbers: NumberSet
+}
+
+// a set of the numbers 0-127 packed into the bits of a u128, so finding the numbers two sets
+// have in common is one AND and counting them is one popcount
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct NumberSet(u128);
+
+impl NumberSet {
+    const MAX: u32 = 127;
+
+    fn insert(&mut self, number: u32) -> Result<(), String> {
+        if number > Self::MAX {
+            return Err(format!("Number out of range 0-{}: {}", Self::MAX, number))
+        }
+        self.0 |= 1 << number;
+        Ok(())
+    }
+
+    fn intersection(&self, other: &NumberSet) -> NumberSet {
+        NumberSet(self.0 & other.0)
+    }
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    // the numbers in the set from smallest to largest
+    #[cfg(test)]
+    fn to_vec(self) -> Vec<u32> {
+        (0..=Self::MAX).filter(|n| self.0 & (1 << n) != 0).collect()
+    }
 }
 
 impl Card {
 
     // count the number of my_numbers that are found in winning_numbers
     fn count_matches(&self) -> usize {
-        self.my_numbers.iter()
-                       .filter(|my_number| {
-                           self.winning_numbers.contains(my_number)
-                       })
-                       .count()
+        self.my_numbers.intersection(&self.winning_numbers).len()
+    }
+
+    // the first match is worth 1 point and each one after that doubles it. None if it
+    // doesn't fit in a u64, which would take more than 64 matches
+    fn score(&self) -> Option<u64> {
+        match self.count_matches() {
+            0     => Some(0),
+            count => 1u64.checked_shl(count as u32 - 1)
+        }
     }
 }
 
+// how the piles of scratchcards grew in part 2
+struct Cascade {
+    // how many of each card we ended up with. a card's pile is final by the time we get to it,
+    // since only the cards before it can add to it
+    copies: Vec<u32>,
+
+    // how many numbers matched on each card
+    matches: Vec<usize>,
+
+    // the indices of the cards each card won copies of, empty if it didn't win any
+    awarded: Vec<Range<usize>>,
+
+    // the indices of cards whose matches would have won copies of cards past the end of the
+    // table. the puzzle promises this never happens, so we stop at the last card and report it
+    overflows: Vec<usize>
+}
+
 // Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
-fn part1(input: &Input) -> u32 {
-    
-    let winning_counts =
-        input.cards.iter()
-                   .map(|card| card.count_matches())
-                   .filter(|count| *count > 0);
-
-    winning_counts.map(|count| 2u32.pow(count as u32 - 1))
-                  .sum()
+fn part1(input: &Input) -> Result<u64, String> {
+    input.cards.iter()
+               .enumerate()
+               .try_fold(0u64, |sum, (i, card)| {
+                   card.score()
+                       .and_then(|score| sum.checked_add(score))
+                       .ok_or(format!("Card {} has {} matches, too many for its score to fit in a u64",
+                                      i + 1, card.count_matches()))
+               })
 }
 
 fn part2(input: &Input) -> u32 {
+    cascade(input).copies.iter().sum()
+}
+
+fn cascade(input: &Input) -> Cascade {
 
     // start out with 1 copy of every scratchcard
     let mut copies: Vec<u32> = vec![ 1; input.cards.len() ];
+    let mut overflows: Vec<usize> = vec![];
+    let mut matches: Vec<usize> = vec![];
+    let mut awarded: Vec<Range<usize>> = vec![];
 
     for (i, card) in input.cards.iter().enumerate() {
         let matching_count = card.count_matches();
-        
+        matches.push(matching_count);
+
+        // clamp to the last card rather than indexing past the end of the piles
+        let last = (i + matching_count).min(copies.len() - 1);
+        if i + matching_count > last {
+            overflows.push(i)
+        }
+
         // add copies to the next matching_count piles
-        for j in i+1 ..= i+matching_count {
+        for j in i+1 ..= last {
             copies[j] += copies[i]
         }
+
+        awarded.push(i+1 .. last+1);
     }
 
-    copies.iter().sum()
+    Cascade { copies, matches, awarded, overflows }
+}
+
+// one row per card, numbered from 1 like the puzzle. the awarded columns are blank for cards
+// that didn't win any copies
+fn trace_csv(cascade: &Cascade) -> String {
+    let mut out = String::from("card,matches,copies,awarded_from,awarded_to\n");
+
+    for (i, range) in cascade.awarded.iter().enumerate() {
+        let (from, to) = if range.is_empty() { (String::new(), String::new()) }
+                         else                { ((range.start + 1).to_string(), range.end.to_string()) };
+
+        out += &format!("{},{},{},{},{}\n", i + 1, cascade.matches[i], cascade.copies[i], from, to);
+    }
+
+    out
+}
+
+// a graphviz digraph with a node per card and an edge to each card it won copies of, labelled
+// with how many copies it added to that pile
+fn trace_dot(cascade: &Cascade) -> String {
+    let mut out = String::from("digraph cascade {\n    node [shape=box];\n");
+
+    for i in 0..cascade.copies.len() {
+        out += &format!("    card{} [label=\"Card {}\\n{} matches\\n{} copies\"];\n",
+                        i + 1, i + 1, cascade.matches[i], cascade.copies[i]);
+    }
+
+    for (i, range) in cascade.awarded.iter().enumerate() {
+        for j in range.clone() {
+            out += &format!("    card{} -> card{} [label=\"+{}\"];\n", i + 1, j + 1, cascade.copies[i]);
+        }
+    }
+
+    out += "}\n";
+    out
 }
 
 
@@ -102,12 +226,16 @@ impl FromStr for Card {
         }
 
         // Parse each set of numbers
-        let parse_numbers = |s: &str| -> Result<Vec<u32>, ParseIntError> {
-            s.trim().split_whitespace().map(|num| num.parse::<u32>()).collect()
+        let parse_numbers = |s: &str| -> Result<NumberSet, String> {
+            let mut set = NumberSet::default();
+            for num in s.split_whitespace() {
+                set.insert(num.parse::<u32>().map_err(|e: ParseIntError| e.to_string())?)?;
+            }
+            Ok(set)
         };
 
-        let winning_numbers = parse_numbers(number_sets[0]).map_err(|e| e.to_string())?;
-        let my_numbers      = parse_numbers(number_sets[1]).map_err(|e| e.to_string())?;
+        let winning_numbers = parse_numbers(number_sets[0])?;
+        let my_numbers      = parse_numbers(number_sets[1])?;
 
         // Construct and return a Card instance
         Ok(Card {
@@ -127,7 +255,7 @@ mod tests {
     #[test]
     fn test_sample_part1() {
         let input = input_from("sample.txt").unwrap();
-        assert_eq!(13, part1(&input));
+        assert_eq!(Ok(13), part1(&input));
     }
 
     #[test]
@@ -136,10 +264,35 @@ mod tests {
         assert_eq!(30, part2(&input));
     }
 
+    #[test]
+    fn test_trace_csv() {
+        let input = input_from("sample.txt").unwrap();
+        let csv = trace_csv(&cascade(&input));
+
+        assert_eq!("card,matches,copies,awarded_from,awarded_to\n\
+                    1,4,1,2,5\n\
+                    2,2,2,3,4\n\
+                    3,2,4,4,5\n\
+                    4,1,8,5,5\n\
+                    5,0,14,,\n\
+                    6,0,1,,\n", csv);
+    }
+
+    #[test]
+    fn test_trace_dot() {
+        let input = input_from("sample.txt").unwrap();
+        let dot = trace_dot(&cascade(&input));
+
+        assert!(dot.starts_with("digraph cascade {\n"));
+        assert!(dot.contains("    card4 [label=\"Card 4\\n1 matches\\n8 copies\"];\n"));
+        assert!(dot.contains("    card3 -> card5 [label=\"+4\"];\n"));
+        assert_eq!(4 + 2 + 2 + 1, dot.matches(" -> ").count());
+    }
+
     #[test]
     fn test_part1() {
         let input = input_from("input.txt").unwrap();
-        assert_eq!(33950, part1(&input));
+        assert_eq!(Ok(33950), part1(&input));
     }
 
     #[test]
@@ -153,8 +306,51 @@ mod tests {
         let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
         let card = input.parse::<Card>().unwrap();
 
-        assert_eq!(card.winning_numbers, vec![41, 48, 83, 86, 17]);
-        assert_eq!(card.my_numbers, vec![83, 86, 6, 31, 17, 9, 48, 53]);
+        assert_eq!(card.winning_numbers.to_vec(), vec![17, 41, 48, 83, 86]);
+        assert_eq!(card.my_numbers.to_vec(), vec![6, 9, 17, 31, 48, 53, 83, 86]);
+        assert_eq!(card.count_matches(), 4);
+    }
+
+    #[test]
+    fn test_number_out_of_range() {
+        let input = "Card 1: 41 48 | 83 128";
+        let result = input.parse::<Card>();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_big_scores() {
+        // 40 matches would overflow 2u32.pow but fits in a u64
+        let numbers: Vec<String> = (1..=40).map(|n| n.to_string()).collect();
+        let line = format!("Card 1: {} | {}", numbers.join(" "), numbers.join(" "));
+        let card = line.parse::<Card>().unwrap();
+
+        assert_eq!(Some(1 << 39), card.score());
+        assert_eq!(Ok(1 << 39), part1(&Input { cards: vec![card] }));
+
+        // 65 matches is a valid card but its score needs 65 bits
+        let numbers: Vec<String> = (1..=65).map(|n| n.to_string()).collect();
+        let line = format!("Card 1: {} | {}", numbers.join(" "), numbers.join(" "));
+        let card = line.parse::<Card>().unwrap();
+
+        assert_eq!(None, card.score());
+        assert_eq!(Err("Card 2 has 65 matches, too many for its score to fit in a u64".to_string()),
+                   part1(&Input { cards: vec!["Card 1: 1 | 2".parse().unwrap(), card] }));
+    }
+
+    #[test]
+    fn test_cascade_past_the_end() {
+        // the second card wins 3 copies but there's only one card after it
+        let input = Input {
+            cards: vec!["Card 1: 1 | 1".parse().unwrap(),
+                        "Card 2: 1 2 3 | 1 2 3".parse().unwrap(),
+                        "Card 3: 1 | 2".parse().unwrap()]
+        };
+
+        let cascade = cascade(&input);
+        assert_eq!(vec![1, 2, 3], cascade.copies);
+        assert_eq!(vec![1], cascade.overflows);
+        assert_eq!(6, part2(&input));
     }
 
     #[test]
@@ -4,6 +4,37 @@ use std::str::FromStr;
 
 fn main() -> Result<(), String> {
 
+    // day05 [--file <file>] [--from water --to humidity] --lookup <n> --reverse-lookup <n> ...
+    // answers queries against the composed map instead of solving. the lookups can be repeated
+    let options = Options::from_args(std::env::args().skip(1))?;
+
+    // day05 [--file <file>] --validate checks the mapping tables and reports their coverage
+    if options.validate {
+        print!("{}", validate(&unchecked_input_from(&options.file)?));
+        return Ok(())
+    }
+
+    if !options.queries.is_empty() {
+        let input = input_from(&options.file)?;
+        let pipeline = Pipeline::from_steps(&input.chain(&options.from, &options.to)?);
+        let (from, to) = (&options.from, &options.to);
+
+        for query in &options.queries {
+            match *query {
+                Query::Lookup(n) =>
+                    println!("{} {} -> {} {}", from, n, to, pipeline.lookup(n)),
+                Query::ReverseLookup(n) => {
+                    let sources: Vec<String> = pipeline.reverse_lookup(n)
+                                                       .iter()
+                                                       .map(|source| source.to_string())
+                                                       .collect();
+                    println!("{} {} <- {} {}", to, n, from, sources.join(", "))
+                }
+            }
+        }
+        return Ok(())
+    }
+
     let sample = input_from("sample.txt")?;
     println!("Sample part 1: {}", part1(&sample)); // 35
     println!("Sample part 2: {}", part2(&sample)); //
@@ -22,15 +53,62 @@ struct Input {
 
 // one of the "from-to map" blocks
 struct MappingStep {
+    from: String,
+    to: String,
     ranges: Vec<Range>
 }
 
+#[derive(Clone, Debug, PartialEq)]
 struct Range {
     dest: usize,
     source: usize,
     size: usize
 }
 
+// a chain of mapping steps collapsed into a single map, eg all seven from seed to location,
+// built once and then reused for as many queries as we like
+struct Pipeline {
+    // the composed step. its ranges don't overlap and are sorted by source so we can binary
+    // search them, and numbers between them map to themselves as usual
+    composed: MappingStep,
+
+    // the whole number line split into linear pieces, identity gaps included, sorted by dest.
+    // these are what we search to go backwards from a location to its seeds
+    by_dest: Vec<Range>
+}
+
+enum Query {
+    Lookup(usize),
+    ReverseLookup(usize)
+}
+
+// command line options
+//   --file <file>                the almanac to query, input.txt by default
+//   --from <category>            the category to map from, seed by default
+//   --to <category>              the category to map to, location by default
+//   --lookup <n>                 where this number ends up
+//   --reverse-lookup <n>         which numbers end up here
+//   --validate                   check the mapping tables for problems and report their coverage
+struct Options {
+    file: String,
+    from: String,
+    to: String,
+    queries: Vec<Query>,
+    validate: bool
+}
+
+// something wrong with the ranges in a mapping step. the usizes are indices into its ranges
+#[derive(Debug, PartialEq)]
+enum Problem {
+    // map_number takes the first range that matches, so the answer depends on the order
+    Overlap(usize, usize),
+
+    // the source or dest plus the size doesn't fit in a usize
+    Overflow(usize),
+
+    ZeroSize(usize)
+}
+
 impl MappingStep {
     fn map_number(&self, n: usize) -> usize {
         self.ranges
@@ -40,9 +118,143 @@ impl MappingStep {
     }
 }
 
+impl MappingStep {
+    // the identity map on a category, which is where composing a chain of steps starts from
+    fn identity(category: &str) -> Self {
+        MappingStep {
+            from: category.to_string(),
+            to: category.to_string(),
+            ranges: vec![]
+        }
+    }
+
+    // split the whole number line into pieces that each map linearly, with the numbers between
+    // our ranges as identity pieces. cutting at every range boundary means each piece lies
+    // entirely inside or outside each range, so we can map a whole piece by its first number
+    // and get the same answer map_number would give for all of it, even with overlapping ranges
+    fn pieces(&self) -> Vec<Range> {
+        let mut cuts: Vec<usize> = vec![0, usize::MAX];
+        for range in &self.ranges {
+            cuts.push(range.source);
+            cuts.push(range.end());
+        }
+        cuts.sort();
+        cuts.dedup();
+
+        let pieces = cuts.windows(2)
+                         .map(|w| Range {
+                             dest: self.map_number(w[0]),
+                             source: w[0],
+                             size: w[1] - w[0]
+                         })
+                         .collect();
+
+        merge_pieces(pieces)
+    }
+
+    // the step that does this one and then the next one
+    fn then(&self, next: &MappingStep) -> MappingStep {
+        let next_pieces = next.pieces();
+        let mut ranges: Vec<Range> = vec![];
+
+        for piece in self.pieces() {
+            // where this piece lands, which we then carve up by the next step's pieces
+            let (lo, hi) = (piece.dest, piece.dest.saturating_add(piece.size));
+
+            for next_piece in &next_pieces {
+                let from = lo.max(next_piece.source);
+                let to   = hi.min(next_piece.end());
+                if from >= to { continue }
+
+                ranges.push(Range {
+                    dest: next_piece.dest + (from - next_piece.source),
+                    source: piece.source + (from - piece.dest),
+                    size: to - from
+                })
+            }
+        }
+
+        ranges.sort_by_key(|range| range.source);
+
+        // the identity pieces don't need to be stored, numbers not in a range map to themselves
+        let ranges = merge_pieces(ranges).into_iter()
+                                         .filter(|range| range.dest != range.source)
+                                         .collect();
+
+        MappingStep {
+            from: self.from.clone(),
+            to: next.to.clone(),
+            ranges
+        }
+    }
+}
+
+// join up neighbouring pieces that continue the same linear map. they must be sorted by source
+fn merge_pieces(pieces: Vec<Range>) -> Vec<Range> {
+    let mut merged: Vec<Range> = Vec::with_capacity(pieces.len());
+
+    for piece in pieces {
+        if let Some(last) = merged.last_mut() {
+            if last.end() == piece.source && last.dest.checked_add(last.size) == Some(piece.dest) {
+                last.size += piece.size;
+                continue
+            }
+        }
+        merged.push(piece)
+    }
+
+    merged
+}
+
+impl Pipeline {
+    // the steps have to be in order, each one picking up where the last one left off
+    fn from_steps(steps: &[&MappingStep]) -> Self {
+        let start = steps.first().map(|step| step.from.as_str()).unwrap_or("");
+        let composed = steps.iter()
+                            .fold(MappingStep::identity(start), |acc, step| acc.then(step));
+
+        let mut by_dest = composed.pieces();
+        by_dest.sort_by_key(|range| range.dest);
+
+        Pipeline { composed, by_dest }
+    }
+
+    // the location a seed ends up at
+    fn lookup(&self, seed: usize) -> usize {
+        // find the last range starting at or before the seed and see if it reaches that far
+        let i = self.composed.ranges.partition_point(|range| range.source <= seed);
+
+        match i {
+            0 => seed,
+            _ => self.composed.ranges[i - 1].map_number(seed).unwrap_or(seed)
+        }
+    }
+
+    // all the seeds that end up at this location, smallest first. the steps aren't necessarily
+    // one-to-one so there could be none or several
+    fn reverse_lookup(&self, location: usize) -> Vec<usize> {
+        let i = self.by_dest.partition_point(|range| range.dest <= location);
+
+        let mut seeds: Vec<usize> =
+            self.by_dest[..i].iter()
+                             .filter(|range| location - range.dest < range.size)
+                             .map(|range| range.source + (location - range.dest))
+                             .collect();
+
+        seeds.sort();
+        seeds
+    }
+}
+
 impl Range {
+    // one past the last source number in this range. saturates instead of overflowing so the
+    // number line ends at usize::MAX
+    fn end(&self) -> usize {
+        self.source.saturating_add(self.size)
+    }
+
     fn map_number(&self, n: usize) -> Option<usize> {
-        if n >= self.source && n < self.source + self.size {
+        if n >= self.source && n < self.end() {
             Some(self.dest + (n - self.source))
         } else {
             None
@@ -50,17 +262,167 @@ impl Range {
     }
 }
 
+impl MappingStep {
+    fn problems(&self) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = vec![];
+
+        for (i, range) in self.ranges.iter().enumerate() {
+            if range.size == 0 {
+                problems.push(Problem::ZeroSize(i))
+            }
+            if range.source.checked_add(range.size).is_none() || range.dest.checked_add(range.size).is_none() {
+                problems.push(Problem::Overflow(i))
+            }
+        }
+
+        for (i, a) in self.ranges.iter().enumerate() {
+        for (j, b) in self.ranges.iter().enumerate().skip(i + 1) {
+            if a.source.max(b.source) < a.end().min(b.end()) {
+                problems.push(Problem::Overlap(i, j))
+            }
+        }}
+
+        problems
+    }
+
+    // the source numbers covered by at least one range, as sorted non-overlapping (start, end)
+    // pairs with the end exclusive
+    fn covered(&self) -> Vec<(usize, usize)> {
+        let mut spans: Vec<(usize, usize)> =
+            self.ranges.iter()
+                       .filter(|range| range.size > 0)
+                       .map(|range| (range.source, range.end()))
+                       .collect();
+        spans.sort();
+
+        let mut covered: Vec<(usize, usize)> = vec![];
+        for (start, end) in spans {
+            match covered.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _                             => covered.push((start, end))
+            }
+        }
+
+        covered
+    }
+
+    // the numbers below the top of the highest range that no range covers, so they map to
+    // themselves. everything from the top of the highest range up is an identity gap too
+    fn identity_gaps(&self) -> Vec<(usize, usize)> {
+        let mut gaps: Vec<(usize, usize)> = vec![];
+        let mut next = 0;
+
+        for (start, end) in self.covered() {
+            if start > next { gaps.push((next, start)) }
+            next = end;
+        }
+
+        gaps
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // number the ranges from 1 like lines in the block
+        match self {
+            Problem::Overlap(i, j) => write!(f, "ranges {} and {} overlap", i + 1, j + 1),
+            Problem::Overflow(i)   => write!(f, "range {} overflows", i + 1),
+            Problem::ZeroSize(i)   => write!(f, "range {} has size zero", i + 1)
+        }
+    }
+}
+
+// check each mapping step for problems and report how much of the number line its ranges cover,
+// along with the seed totals for both readings of the seeds line
+fn validate(input: &Input) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let spans = |spans: &[(usize, usize)]| -> String {
+        spans.iter()
+             .map(|(start, end)| format!("{}..{}", start, end))
+             .collect::<Vec<String>>()
+             .join(" ")
+    };
+
+    // part 2 reads the seeds line as pairs of start and length. this is what sum_ranges.pl did
+    let seed_ranges: u128 = input.seeds.chunks(2)
+                                       .filter_map(|pair| pair.get(1))
+                                       .map(|&size| size as u128)
+                                       .sum();
+
+    writeln!(out, "seeds: {} numbers, or {} ranges totalling {} seeds",
+             input.seeds.len(), input.seeds.len() / 2, seed_ranges).unwrap();
+
+    let mut total_problems = 0;
+
+    for step in &input.maps {
+        let covered = step.covered();
+        let problems = step.problems();
+        let total: u128 = covered.iter().map(|(start, end)| (end - start) as u128).sum();
+        let sizes: u128 = step.ranges.iter().map(|range| range.size as u128).sum();
+
+        writeln!(out).unwrap();
+        writeln!(out, "{}-to-{}: {} ranges, sizes totalling {}, covering {} numbers",
+                 step.from, step.to, step.ranges.len(), sizes, total).unwrap();
+        writeln!(out, "  covered: {}", spans(&covered)).unwrap();
+
+        let gaps = step.identity_gaps();
+        if !gaps.is_empty() {
+            writeln!(out, "  identity gaps: {}", spans(&gaps)).unwrap();
+        }
+
+        for problem in &problems {
+            writeln!(out, "  problem: {}", problem).unwrap();
+        }
+        total_problems += problems.len();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "{} problems found", total_problems).unwrap();
+    out
+}
+
 // map each of the top seed numbers through the mapping steps, one at a time
 fn part1(input: &Input) -> usize {
+    // the parse already checked that this chain exists
+    let chain = input.chain("seed", "location").unwrap();
+
     input.seeds.iter()
                .map(|&seed| {
-                   input.maps.iter()
-                             .fold(seed, |acc, map| map.map_number(acc))
+                   chain.iter()
+                        .fold(seed, |acc, map| map.map_number(acc))
                })
                .min()
                .unwrap()
 }
 
+impl Input {
+    // the mapping steps that take us from one category to another, found by following the
+    // category names rather than relying on the order of the blocks in the file
+    fn chain(&self, from: &str, to: &str) -> Result<Vec<&MappingStep>, String> {
+        let mut chain: Vec<&MappingStep> = vec![];
+        let mut category = from;
+
+        while category != to {
+            let step = self.maps.iter()
+                                .find(|step| step.from == category)
+                                .ok_or(format!("No map from {} on the way from {} to {}", category, from, to))?;
+
+            // coming back around to a category we've already left means we'll never get there
+            if step.to == from || chain.iter().any(|seen| seen.to == step.to) {
+                return Err(format!("Maps from {} to {} go around in a cycle at {}-to-{}",
+                                   from, to, step.from, step.to))
+            }
+
+            chain.push(step);
+            category = &step.to;
+        }
+
+        Ok(chain)
+    }
+}
+
 // the seed numbers were actually ranges... about 10^9 now to check
 fn part2(input: &Input) -> u32 {
     0
@@ -103,6 +465,8 @@ impl FromStr for MappingStep {
         if lines.len() < 2 {
             return Err("Need at least 2 lines, one for the map title, the rest for ranges".to_string())
         }
+
+        let (from, to) = parse_title(lines[0])?;
         
         let mut ranges = Vec::new();
 
@@ -112,10 +476,22 @@ impl FromStr for MappingStep {
             ranges.push(range);
         }
 
-        Ok(MappingStep { ranges })
+        Ok(MappingStep {
+            from: from.to_string(),
+            to: to.to_string(),
+            ranges
+        })
     }
 }
 
+// soil-to-fertilizer map:
+fn parse_title(line: &str) -> Result<(&str, &str), String> {
+    line.strip_suffix(" map:")
+        .and_then(|title| title.split_once("-to-"))
+        .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+        .ok_or(format!("Expected a title like 'soil-to-fertilizer map:' but found '{}'", line))
+}
+
 impl FromStr for Input {
     type Err = String;
 
@@ -128,13 +504,26 @@ impl FromStr for Input {
 
         let mut maps: Vec<MappingStep> = Vec::new();
         for segment in segments[1..].iter() {
-            maps.push(segment.parse::<MappingStep>()?)
+            let step = segment.parse::<MappingStep>()?;
+
+            // we follow the maps by their source category so there can only be one from each
+            if let Some(other) = maps.iter().find(|other| other.from == step.from) {
+                return Err(format!("Duplicate maps from {}: {}-to-{} and {}-to-{}",
+                                   step.from, other.from, other.to, step.from, step.to))
+            }
+
+            maps.push(step)
         }
 
-        Ok(Input {
+        let input = Input {
             seeds: parse_seeds(segments[0])?,
             maps
-        })
+        };
+
+        // make sure the seeds can get to their locations now rather than finding out later
+        input.chain("seed", "location")?;
+
+        Ok(input)
     }
 }
 
@@ -149,8 +538,61 @@ fn parse_seeds(s: &str) -> Result<Vec<usize>, String> {
          .collect()
 }
 
+impl Options {
+    fn from_args(mut args: impl Iterator<Item=String>) -> Result<Self, String> {
+        let mut options = Options {
+            file: "input.txt".to_string(),
+            from: "seed".to_string(),
+            to: "location".to_string(),
+            queries: vec![],
+            validate: false
+        };
+
+        let number = |arg: &str, value: Option<String>| -> Result<usize, String> {
+            let value = value.ok_or(format!("Expected a number after {}", arg))?;
+            value.parse().map_err(|_| format!("Invalid number after {}: {}", arg, value))
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--file"           => options.file = args.next().ok_or("Expected a file after --file")?,
+                "--from"           => options.from = args.next().ok_or("Expected a category after --from")?,
+                "--to"             => options.to   = args.next().ok_or("Expected a category after --to")?,
+                "--lookup"         => options.queries.push(Query::Lookup(number(&arg, args.next())?)),
+                "--reverse-lookup" => options.queries.push(Query::ReverseLookup(number(&arg, args.next())?)),
+                "--validate"       => options.validate = true,
+                _                  => return Err(format!("Unknown argument: {}", arg))
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+impl Input {
+    // a range whose sources run past usize::MAX just stops there, but one whose destinations
+    // do can't be mapped at all. --validate reports these along with everything else, but
+    // anything that maps numbers has to turn them away
+    fn check_mappable(&self) -> Result<(), String> {
+        for step in &self.maps {
+            if let Some(i) = step.ranges.iter().position(|range| range.dest.checked_add(range.size).is_none()) {
+                return Err(format!("Range {} of the {}-to-{} map goes past the largest number",
+                                   i + 1, step.from, step.to))
+            }
+        }
+        Ok(())
+    }
+}
+
 // Read input from a file
 fn input_from(file: &str) -> Result<Input, String> {
+    let input = unchecked_input_from(file)?;
+    input.check_mappable()?;
+    Ok(input)
+}
+
+// the input as it is, even if some of its ranges can't be mapped, for --validate to look over
+fn unchecked_input_from(file: &str) -> Result<Input, String> {
 
     let input = std::fs::read_to_string(file)
         .map_err(|err| format!("Failed to read file: {}", err))?;
@@ -177,6 +619,136 @@ mod tests {
         assert_eq!(46, part2(&input))
     }
 
+    #[test]
+    fn test_composed_lookup() {
+        let input = input_from("sample.txt").unwrap();
+        let pipeline = Pipeline::from_steps(&input.chain("seed", "location").unwrap());
+
+        // the composed map agrees with mapping through each step in turn
+        for seed in 0..200 {
+            let expected = input.maps.iter().fold(seed, |acc, map| map.map_number(acc));
+            assert_eq!(expected, pipeline.lookup(seed));
+        }
+
+        assert_eq!(82, pipeline.lookup(79));
+        assert_eq!(35, pipeline.lookup(13));
+    }
+
+    #[test]
+    fn test_reverse_lookup() {
+        let input = input_from("sample.txt").unwrap();
+        let pipeline = Pipeline::from_steps(&input.chain("seed", "location").unwrap());
+
+        assert_eq!(vec![79], pipeline.reverse_lookup(82));
+        assert_eq!(vec![13], pipeline.reverse_lookup(35));
+
+        for location in 0..200 {
+            for seed in pipeline.reverse_lookup(location) {
+                assert_eq!(location, pipeline.lookup(seed));
+            }
+        }
+    }
+
+    #[test]
+    fn test_then_with_overlaps() {
+        // the first range wins where they overlap, like map_number
+        let step: MappingStep = "a-to-b map:\n100 0 10\n200 5 10".parse().unwrap();
+        let composed = MappingStep::identity("a").then(&step);
+
+        assert_eq!(vec![Range { dest: 100, source: 0, size: 10 },
+                        Range { dest: 205, source: 10, size: 5 }], composed.ranges);
+
+        // swapping two blocks and swapping them back again cancels out
+        let swap: MappingStep = "a-to-b map:\n100 0 10\n0 100 10".parse().unwrap();
+        assert!(swap.then(&swap).ranges.is_empty());
+    }
+
+    #[test]
+    fn test_out_of_order_blocks() {
+        let text = std::fs::read_to_string("sample.txt").unwrap();
+        let mut segments: Vec<&str> = text.split("\n\n").collect();
+        segments[1..].reverse();
+
+        let input = segments.join("\n\n").parse::<Input>().unwrap();
+        assert_eq!("humidity", input.maps[0].from);
+        assert_eq!(35, part1(&input));
+    }
+
+    #[test]
+    fn test_any_category_pair() {
+        let input = input_from("sample.txt").unwrap();
+        let chain = input.chain("water", "humidity").unwrap();
+        let names: Vec<String> = chain.iter().map(|step| format!("{}-to-{}", step.from, step.to)).collect();
+
+        assert_eq!(vec!["water-to-light", "light-to-temperature", "temperature-to-humidity"], names);
+
+        // water 81 -> light 74 -> temperature 78 -> humidity 78, from the puzzle description
+        let pipeline = Pipeline::from_steps(&chain);
+        assert_eq!(78, pipeline.lookup(81));
+    }
+
+    #[test]
+    fn test_chain_errors() {
+        let missing = "seeds: 1\n\nseed-to-soil map:\n1 2 3";
+        assert_eq!(Err("No map from soil on the way from seed to location".to_string()),
+                   missing.parse::<Input>().map(|_| ()));
+
+        let cycle = "seeds: 1\n\nseed-to-soil map:\n1 2 3\n\nsoil-to-seed map:\n1 2 3";
+        assert_eq!(Err("Maps from seed to location go around in a cycle at soil-to-seed".to_string()),
+                   cycle.parse::<Input>().map(|_| ()));
+
+        let duplicate = "seeds: 1\n\nseed-to-soil map:\n1 2 3\n\nseed-to-location map:\n1 2 3";
+        assert_eq!(Err("Duplicate maps from seed: seed-to-soil and seed-to-location".to_string()),
+                   duplicate.parse::<Input>().map(|_| ()));
+
+        assert!("seeds: 1\n\nseed to soil map:\n1 2 3".parse::<Input>().is_err());
+
+        let overflow = format!("seeds: 1\n\nseed-to-location map:\n1 2 3\n{} 0 2", usize::MAX - 1);
+        assert_eq!(Err("Range 2 of the seed-to-location map goes past the largest number".to_string()),
+                   overflow.parse::<Input>().unwrap().check_mappable());
+    }
+
+    #[test]
+    fn test_validate_dest_overflow() {
+        // the destinations of the second range run past usize::MAX, which validate should
+        // report rather than stop at
+        let input = format!("seeds: 1\n\nseed-to-location map:\n1 2 3\n{} 10 2", usize::MAX - 1)
+                        .parse::<Input>().unwrap();
+        let report = validate(&input);
+
+        assert!(report.contains("seed-to-location: 2 ranges, sizes totalling 5, covering 5 numbers\n"));
+        assert!(report.contains("  problem: range 2 overflows\n"));
+        assert!(report.ends_with("1 problems found\n"));
+    }
+
+    #[test]
+    fn test_problems() {
+        let step: MappingStep = format!("a-to-b map:\n100 0 10\n200 5 10\n0 50 0\n5 {} 2",
+                                        usize::MAX - 1).parse().unwrap();
+
+        assert_eq!(vec![Problem::ZeroSize(2), Problem::Overflow(3), Problem::Overlap(0, 1)],
+                   step.problems());
+
+        // an overflowing range still maps without panicking
+        assert_eq!(5, step.map_number(usize::MAX - 1));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let input = input_from("sample.txt").unwrap();
+
+        // soil-to-fertilizer covers 0..54 with no gaps, seed-to-soil leaves 0..50 mapping to itself
+        assert_eq!(vec![(0, 54)], input.maps[1].covered());
+        assert!(input.maps[1].identity_gaps().is_empty());
+        assert_eq!(vec![(0, 50)], input.maps[0].identity_gaps());
+
+        let report = validate(&input);
+        assert!(report.starts_with("seeds: 4 numbers, or 2 ranges totalling 27 seeds\n"));
+        assert!(report.contains("seed-to-soil: 2 ranges, sizes totalling 50, covering 50 numbers\n  \
+                                 covered: 50..100\n  identity gaps: 0..50\n"));
+        assert!(report.ends_with("0 problems found\n"));
+    }
+
     #[test]
     fn test_part1() {
         let input = input_from("input.txt").unwrap();
@@ -4,13 +4,20 @@ use std::str::FromStr;
 
 fn main() -> Result<(), String> {
 
+    let options = Options::from_args(std::env::args().skip(1))?;
+    let model = options.model.as_deref();
+
     let sample = input_from("sample.txt")?;
-    println!("Sample part 1: {}", part1(&sample)); // 288
-    println!("Sample part 2: {}", part2(&sample)); // 71503
+    let (one, two) = solve(&sample, model)?;
+    println!("Sample part 1: {}", one); // 288
+    println!("Sample part 2: {}", two); // 71503
+    if options.brute_force { cross_check(&sample, model)? }
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    let (one, two) = solve(&input, model)?;
+    println!("Part 1: {}", one);
+    println!("Part 2: {}", two);
+    if options.brute_force { cross_check(&input, model)? }
 
     Ok(())
 }
@@ -20,59 +27,270 @@ struct Input {
     squished_race: Race    // to be squished together into one combined time/distance 
 }
 
+// u128 so the squished race can be much longer than the puzzle's
 struct Race {
-    time: u64,
-    distance: u64
+    time: u128,
+    distance: u128
+}
+
+// how a boat's speed depends on how long its button was held, for trying out variants of the
+// puzzle. the speed should rise and then level off or fall again as the hold goes on (ie be
+// concave), which makes the distance curve go up and then down, so we can binary search it
+trait BoatModel {
+    // how far the boat goes in the race if the button is held for hold_delay. the distances
+    // saturate rather than overflow, since a distance that big beats any record anyway
+    fn distance(&self, race: &Race, hold_delay: u128) -> u128;
+}
+
+// the puzzle's boat: one millimetre per millisecond of speed for each millisecond held
+struct LinearCharge;
+
+// rate millimetres per millisecond of speed for each millisecond held
+struct Accelerating {
+    rate: u128
+}
+
+// charges like the puzzle's boat but can't go faster than top_speed
+struct CappedSpeed {
+    top_speed: u128
+}
+
+// the charge leaks away while the button is held, so the speed after holding for h is
+// h*(lifetime-h), peaking halfway through the lifetime and gone entirely by the end of it
+struct DecayingCharge {
+    lifetime: u128
+}
+
+// command line options
+//   --brute-force   check the answers against trying every hold delay
+//   --model <name>  solve with a different boat: linear, accelerating:<rate>, capped:<top speed>
+//                   or decaying:<lifetime>
+struct Options {
+    brute_force: bool,
+    model: Option<Box<dyn BoatModel>>
+}
+
+fn part1(input: &Input) -> Result<u128, String> {
+    product_of_ways(input.races.iter().map(count_winning_delays))
+}
+
+fn part2(input: &Input) -> u128 {
+    count_winning_delays(&input.squished_race)
+}
+
+// multiply together the ways to win each race, which can overflow even a u128 on a big enough sheet
+fn product_of_ways(mut ways: impl Iterator<Item=u128>) -> Result<u128, String> {
+    ways.try_fold(1u128, |product, count| product.checked_mul(count))
+        .ok_or("The product of the ways to win overflows a u128".to_string())
+}
+
+// holding for t of the race's T milliseconds goes t*(T-t) millimetres, an upside-down parabola,
+// so the delays that beat the record distance D are the integers strictly between the roots of
+// t^2 - Tt + D = 0, ie (T ± sqrt(T^2 - 4D)) / 2. we do it all in u128 with an integer square
+// root so there's no floating point rounding to worry about on the big squished race
+fn winning_delays(race: &Race) -> Option<(u128, u128)> {
+    let (time, distance) = (race.time, race.distance);
+
+    // a distance too big for a u128 is certainly bigger than the record
+    let beats = |t: u128| t.checked_mul(time - t).is_none_or(|d| d > distance);
+
+    // the best delay is half the race, and if that doesn't beat the record nothing will
+    let half = time / 2;
+    if !beats(half) { return None }
+
+    // the floor of the lower root, give or take one from the truncated square root. when the
+    // sheet is so big that T^2 doesn't fit in a u128 we binary search for it instead, which
+    // works because the distances only go up on the way to the halfway point
+    let discriminant = time.checked_mul(time)
+                           .zip(distance.checked_mul(4))
+                           .map(|(square, four_d)| square - four_d);
+
+    let mut low = match discriminant {
+        Some(discriminant) => (time - discriminant.isqrt()) / 2,
+        None => {
+            let (mut lo, mut hi) = (0, half);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if beats(mid) { hi = mid } else { lo = mid + 1 }
+            }
+            lo
+        }
+    };
+
+    // step to the first delay that strictly beats the record, so a delay that only ties is
+    // excluded. this can't run past half since we know half wins
+    while low > 0 && beats(low - 1) { low -= 1 }
+    while !beats(low) { low += 1 }
+
+    // the parabola is symmetric about T/2 so the last winning delay mirrors the first
+    Some((low, time - low))
+}
+
+fn count_winning_delays(race: &Race) -> u128 {
+    match winning_delays(race) {
+        Some((low, high)) => high - low + 1,
+        None              => 0
+    }
 }
 
-fn part1(input: &Input) -> usize {
-    let mut ways_to_beat: Vec<usize> = Vec::new();
+// the same as winning_delays but for any boat, by binary searching the distance curve. first for
+// its peak, then up each side of the peak for the delays where it crosses the record
+fn winning_delays_with_model(model: &dyn BoatModel, race: &Race) -> Option<(u128, u128)> {
+    let distance = |t: u128| model.distance(race, t);
+    let beats = |t: u128| distance(t) > race.distance;
+
+    // the first delay where the curve stops going up
+    let (mut lo, mut hi) = (0, race.time);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if distance(mid) < distance(mid + 1) { lo = mid + 1 } else { hi = mid }
+    }
+    let peak = lo;
 
-    for race in &input.races {
-        
-        // brute force it. a couple O(log n) searches would be more efficient here
-        // (i think the delay/distance graph would form an inverted parabola)
-        // but we can do this in about a tenth of a second the easy way
-        let distances: Vec<u64> =
-            (0..race.time)
-                .map(|t| find_distance_given_delay(&race, t))
-                .collect();
+    if !beats(peak) { return None }
 
-        let count_winners: usize =
-            distances.into_iter()
-                     .filter(|d| d > &race.distance)
-                     .count();
+    // the first winning delay on the way up to the peak
+    let (mut lo, mut hi) = (0, peak);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if beats(mid) { hi = mid } else { lo = mid + 1 }
+    }
+    let low = lo;
 
-        ways_to_beat.push(count_winners);
+    // the last winning delay on the way down from the peak
+    let (mut lo, mut hi) = (peak, race.time);
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if beats(mid) { lo = mid } else { hi = mid - 1 }
     }
+    let high = lo;
 
-    ways_to_beat.into_iter()
-                .product()
+    Some((low, high))
 }
 
-fn part2(input: &Input) -> usize {
+fn count_winning_delays_with_model(model: &dyn BoatModel, race: &Race) -> u128 {
+    match winning_delays_with_model(model, race) {
+        Some((low, high)) => high - low + 1,
+        None              => 0
+    }
+}
 
-    let distances: Vec<u64> =
-        (0..input.squished_race.time)
-            .map(|t| find_distance_given_delay(&input.squished_race, t))
-            .collect();
+fn part1_with_model(input: &Input, model: &dyn BoatModel) -> Result<u128, String> {
+    product_of_ways(input.races.iter().map(|race| count_winning_delays_with_model(model, race)))
+}
+
+fn part2_with_model(input: &Input, model: &dyn BoatModel) -> u128 {
+    count_winning_delays_with_model(model, &input.squished_race)
+}
+
+// the closed form for the puzzle's boat, or the binary search for any other
+fn solve(input: &Input, model: Option<&dyn BoatModel>) -> Result<(u128, u128), String> {
+    match model {
+        None        => Ok((part1(input)?, part2(input))),
+        Some(model) => Ok((part1_with_model(input, model)?, part2_with_model(input, model)))
+    }
+}
+
+// the original approach, trying every hold delay. too slow to be the main answer for part 2
+// but it's a handy check on the faster ones
+fn count_winning_delays_brute_force(model: &dyn BoatModel, race: &Race) -> u128 {
+    (0..race.time).filter(|&t| model.distance(race, t) > race.distance)
+                  .count() as u128
+}
+
+fn cross_check(input: &Input, model: Option<&dyn BoatModel>) -> Result<(), String> {
+    for race in input.races.iter().chain(std::iter::once(&input.squished_race)) {
+        let fast = match model {
+            None        => count_winning_delays(race),
+            Some(model) => count_winning_delays_with_model(model, race)
+        };
+        let brute_force = count_winning_delays_brute_force(model.unwrap_or(&LinearCharge), race);
+
+        if fast != brute_force {
+            return Err(format!("Race of time {} and distance {}: fast solver gives {} but brute force gives {}",
+                               race.time, race.distance, fast, brute_force))
+        }
+    }
+
+    println!("Brute force agrees");
+    Ok(())
+}
+
+impl BoatModel for LinearCharge {
+    // chatgpt 4.0
+    fn distance(&self, race: &Race, hold_delay: u128) -> u128 {
+        // Check if the hold delay is equal to or greater than the race time
+        if hold_delay >= race.time {
+            0
+        } else {
+            // Calculate the travel time
+            let travel_time = race.time - hold_delay;
+
+            // Calculate the distance
+            hold_delay.saturating_mul(travel_time)
+        }
+    }
+}
+
+impl BoatModel for Accelerating {
+    fn distance(&self, race: &Race, hold_delay: u128) -> u128 {
+        let speed = self.rate.saturating_mul(hold_delay);
+        speed.saturating_mul(race.time.saturating_sub(hold_delay))
+    }
+}
 
-    distances.into_iter()
-             .filter(|d| d > &input.squished_race.distance)
-             .count()
+impl BoatModel for CappedSpeed {
+    fn distance(&self, race: &Race, hold_delay: u128) -> u128 {
+        let speed = hold_delay.min(self.top_speed);
+        speed.saturating_mul(race.time.saturating_sub(hold_delay))
+    }
 }
 
-// chatgpt 4.0
-fn find_distance_given_delay(race: &Race, hold_delay: u64) -> u64 {
-    // Check if the hold delay is equal to or greater than the race time
-    if hold_delay >= race.time {
-        0
-    } else {
-        // Calculate the travel time
-        let travel_time = race.time - hold_delay;
+impl BoatModel for DecayingCharge {
+    fn distance(&self, race: &Race, hold_delay: u128) -> u128 {
+        let speed = hold_delay.saturating_mul(self.lifetime.saturating_sub(hold_delay));
+        speed.saturating_mul(race.time.saturating_sub(hold_delay))
+    }
+}
 
-        // Calculate the distance
-        hold_delay * travel_time
+// linear, accelerating:3, capped:10 or decaying:20
+fn model_from_str(s: &str) -> Result<Box<dyn BoatModel>, String> {
+    let (name, param) = match s.split_once(':') {
+        Some((name, param)) => (name, Some(param)),
+        None                => (s, None)
+    };
+
+    let param = || -> Result<u128, String> {
+        param.ok_or(format!("Expected a number after the colon: {}", s))?
+             .parse()
+             .map_err(|_| format!("Invalid number in boat model: {}", s))
+    };
+
+    match name {
+        "linear"       => Ok(Box::new(LinearCharge)),
+        "accelerating" => Ok(Box::new(Accelerating { rate: param()? })),
+        "capped"       => Ok(Box::new(CappedSpeed { top_speed: param()? })),
+        "decaying"     => Ok(Box::new(DecayingCharge { lifetime: param()? })),
+        _              => Err(format!("Unknown boat model: {}", s))
+    }
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item=String>) -> Result<Self, String> {
+        let mut options = Options {
+            brute_force: false,
+            model: None
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--brute-force" => options.brute_force = true,
+                "--model"       => options.model = Some(model_from_str(&args.next().ok_or("Expected a boat model after --model")?)?),
+                _               => return Err(format!("Unknown argument: {}", arg))
+            }
+        }
+
+        Ok(options)
     }
 }
 
@@ -82,6 +300,10 @@ fn find_distance_given_delay(race: &Race, hold_delay: u64) -> u64 {
 impl FromStr for Input {
     type Err = String;
 
+    /*
+        Time:      7  15   30
+        Distance:  9  40  200
+    */
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let lines: Vec<&str> = s.lines().collect();
 
@@ -89,22 +311,27 @@ impl FromStr for Input {
             return Err("Expected two lines in input".to_string())
         }
 
-        // first parse the individual races, do the combined one after
-        let times     = line_to_ints(lines[0])?;
-        let distances = line_to_ints(lines[1])?;
-
-        let mut races: Vec<Race> = Vec::new();
+        let time_columns     = line_to_columns(lines[0], "Time:")?;
+        let distance_columns = line_to_columns(lines[1], "Distance:")?;
 
-        for (i, &time) in times.iter().enumerate() {
-            races.push(Race {
-                time,
-                distance: distances[i]
-            })
+        if time_columns.len() != distance_columns.len() {
+            return Err(format!("Found {} times but {} distances, expected one of each per race",
+                               time_columns.len(), distance_columns.len()))
         }
 
+        // first parse the individual races, do the combined one after
+        let times     = columns_to_ints(&time_columns)?;
+        let distances = columns_to_ints(&distance_columns)?;
+
+        let races: Vec<Race> =
+            times.into_iter()
+                 .zip(distances)
+                 .map(|(time, distance)| Race { time, distance })
+                 .collect();
+
         // parse the combined race by removing the whitespace between numbers
-        let squished_time     = line_to_squished_int(lines[0]);
-        let squished_distance = line_to_squished_int(lines[1]);
+        let squished_time     = columns_to_squished_int(&time_columns);
+        let squished_distance = columns_to_squished_int(&distance_columns);
 
         Ok(Input {
             races,
@@ -116,26 +343,32 @@ impl FromStr for Input {
     }
 }
 
-fn line_to_ints(line: &str) -> Result<Vec<u64>, String> {
-    let split: Vec<&str> = line.split_whitespace().collect();
+// check the line starts with its label and split up the numbers after it
+fn line_to_columns<'a>(line: &'a str, label: &str) -> Result<Vec<&'a str>, String> {
+    let columns: Vec<&str> =
+        line.strip_prefix(label)
+            .ok_or(format!("Expected the line to start with '{}': {}", label, line))?
+            .split_whitespace()
+            .collect();
 
-    let ints: Result<Vec<u64>, _> =
-        split[1..].iter()
-                  .map(|s| s.parse::<u64>().map_err(|_| "Couldn't parse u64"))
-                  .collect();
+    if columns.is_empty() {
+        return Err(format!("No numbers after '{}'", label))
+    }
 
-    let ints: Vec<u64> = ints?;
+    Ok(columns)
+}
 
-    Ok(ints)
+fn columns_to_ints(columns: &[&str]) -> Result<Vec<u128>, String> {
+    columns.iter()
+           .map(|s| s.parse::<u128>().map_err(|_| format!("Couldn't parse a number: {}", s)))
+           .collect()
 }
 
-fn line_to_squished_int(line: &str) -> Result<u64, String> {
-    let combined = line.chars()
-                       .filter(|&c| c.is_ascii_digit())
-                       .collect::<String>();
+fn columns_to_squished_int(columns: &[&str]) -> Result<u128, String> {
+    let combined = columns.concat();
 
-    combined.parse::<u64>()
-            .map_err(|e| format!("Error parsing combined integer: {}", e.to_string()))
+    combined.parse::<u128>()
+            .map_err(|e| format!("Error parsing combined integer {}: {}", combined, e))
 }
 
 // Read input from a file
@@ -157,7 +390,7 @@ mod tests {
     #[test]
     fn test_sample_part1() {
         let input = input_from("sample.txt").unwrap();
-        assert_eq!(288, part1(&input))
+        assert_eq!(Ok(288), part1(&input))
     }
 
     #[test]
@@ -166,10 +399,127 @@ mod tests {
         assert_eq!(71503, part2(&input))
     }
 
+    #[test]
+    fn test_winning_delays() {
+        // the sample's third race ties the record at 10 and 20, which don't count
+        assert_eq!(Some((11, 19)), winning_delays(&Race { time: 30, distance: 200 }));
+        assert_eq!(Some((2, 5)),   winning_delays(&Race { time: 7, distance: 9 }));
+
+        // the best you can do is exactly the record, or not even that
+        assert_eq!(None, winning_delays(&Race { time: 10, distance: 25 }));
+        assert_eq!(None, winning_delays(&Race { time: 10, distance: 26 }));
+        assert_eq!(None, winning_delays(&Race { time: 0, distance: 0 }));
+    }
+
+    #[test]
+    fn test_closed_form_matches_brute_force() {
+        // every record from zero to just past the best possible distance for a range of times
+        for time in 0..80 {
+            for distance in 0 ..= time * time / 4 + 2 {
+                let race = Race { time, distance };
+                assert_eq!(count_winning_delays_brute_force(&LinearCharge, &race), count_winning_delays(&race),
+                           "time {} distance {}", time, distance);
+            }
+        }
+    }
+
+    #[test]
+    fn test_models_match_brute_force() {
+        let models: Vec<Box<dyn BoatModel>> = ["linear", "accelerating:3", "capped:5", "capped:100",
+                                               "decaying:12", "decaying:100", "decaying:0"]
+            .iter()
+            .map(|s| model_from_str(s).unwrap())
+            .collect();
+
+        for model in &models {
+        for time in 0..40 {
+            let best = (0..=time).map(|t| model.distance(&Race { time, distance: 0 }, t)).max().unwrap();
+
+            for distance in (0 ..= best + 1).step_by(1 + best as usize / 50) {
+                let race = Race { time, distance };
+                assert_eq!(count_winning_delays_brute_force(model.as_ref(), &race),
+                           count_winning_delays_with_model(model.as_ref(), &race),
+                           "time {} distance {}", time, distance);
+            }
+        }}
+    }
+
+    #[test]
+    fn test_linear_model_matches_closed_form() {
+        let input = input_from("sample.txt").unwrap();
+
+        assert_eq!(Ok(288), part1_with_model(&input, &LinearCharge));
+        assert_eq!(71503, part2_with_model(&input, &LinearCharge));
+
+        let race = Race { time: u64::MAX as u128, distance: 1 << 120 };
+        assert_eq!(winning_delays(&race), winning_delays_with_model(&LinearCharge, &race));
+    }
+
+    #[test]
+    fn test_model_from_str() {
+        let race = Race { time: 10, distance: 0 };
+
+        assert_eq!(21, model_from_str("accelerating:1").unwrap().distance(&race, 3));
+        assert_eq!(63, model_from_str("accelerating:3").unwrap().distance(&race, 3));
+        assert_eq!(14, model_from_str("capped:2").unwrap().distance(&race, 3));
+        assert_eq!(63, model_from_str("decaying:6").unwrap().distance(&race, 3));
+
+        assert!(model_from_str("capped").is_err());
+        assert!(model_from_str("capped:x").is_err());
+        assert!(model_from_str("rocket:3").is_err());
+    }
+
+    #[test]
+    fn test_huge_race() {
+        // near the top of u64 the squares only fit in u128. a record of zero means every delay
+        // except 0 and T wins
+        let race = Race { time: u64::MAX as u128, distance: 0 };
+        assert_eq!(u64::MAX as u128 - 1, count_winning_delays(&race));
+
+        // and past that even the squares don't fit
+        let race = Race { time: u128::MAX, distance: 0 };
+        assert_eq!(u128::MAX - 1, count_winning_delays(&race));
+
+        let race = Race { time: 1 << 70, distance: (1 << 127) + 12345 };
+        let (low, high) = winning_delays(&race).unwrap();
+        assert!(low * (race.time - low) > race.distance);
+        assert!((low - 1) * (race.time - low + 1) <= race.distance);
+        assert_eq!(race.time - low, high);
+    }
+
+    #[test]
+    fn test_squished_past_u64() {
+        let input = "Time:      7000000000  15000000000\nDistance:  9  40".parse::<Input>().unwrap();
+        assert_eq!(700000000015000000000, input.squished_race.time);
+        assert_eq!(940, input.squished_race.distance);
+
+        // every delay but the very first and last beats a record of 940
+        assert_eq!(700000000014999999999, part2(&input));
+
+        // the product of the two races' ways to win is already past a u64
+        assert_eq!(Ok(6999999999 * 14999999999), part1(&input));
+        assert_eq!(Err("The product of the ways to win overflows a u128".to_string()),
+                   product_of_ways([u128::MAX / 2, 3].into_iter()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |s: &str| s.parse::<Input>().map(|_| ());
+
+        assert_eq!(Err("Found 3 times but 2 distances, expected one of each per race".to_string()),
+                   parse("Time: 7 15 30\nDistance: 9 40"));
+        assert_eq!(Err("Expected the line to start with 'Distance:': Time: 9 40".to_string()),
+                   parse("Time: 7 15\nTime: 9 40"));
+        assert_eq!(Err("No numbers after 'Time:'".to_string()),
+                   parse("Time:\nDistance: 9"));
+        assert!(parse("7 15\n9 40").is_err());
+        assert!(parse("Time: 7 x\nDistance: 9 40").is_err());
+    }
+
     #[test]
     fn test_part1() {
         let input = input_from("input.txt").unwrap();
-        assert_eq!(131376, part1(&input))
+        assert_eq!(Ok(131376), part1(&input))
     }
 
     #[test]
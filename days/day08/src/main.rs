@@ -2,19 +2,58 @@
 
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::str::FromStr;
 
 fn main() -> Result<(), String> {
 
+    // instead of solving, these look at the shape of the network:
+    //   day08 --dot [--cycles] [file]    writes the network out for graphviz
+    //   day08 --path <from> <to> [file]  shortest path between two nodes, ignoring the instructions
+    //   day08 --reachable [file]         every node we can get to from AAA
+    //   day08 --scc [file]               the strongly connected components
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |i: usize| args.get(i).map(String::as_str);
+
+    match arg(1) {
+        Some("--dot") => {
+            let cycles = arg(2) == Some("--cycles");
+            let file = arg(if cycles { 3 } else { 2 }).unwrap_or("input.txt");
+
+            print!("{}", to_dot(&input_from(file)?, cycles));
+            return Ok(())
+        }
+        Some("--path") => {
+            let from = arg(2).ok_or("Expected a node to start from after --path")?;
+            let to   = arg(3).ok_or("Expected a node to end at after --path")?;
+            let network = input_from(arg(4).unwrap_or("input.txt"))?.network;
+
+            println!("{}", describe_path(&network, from, to)?);
+            return Ok(())
+        }
+        Some("--reachable") => {
+            let network = input_from(arg(2).unwrap_or("input.txt"))?.network;
+
+            print!("{}", describe_reachable(&network, "AAA")?);
+            return Ok(())
+        }
+        Some("--scc") => {
+            let network = input_from(arg(2).unwrap_or("input.txt"))?.network;
+
+            print!("{}", describe_components(&network));
+            return Ok(())
+        }
+        _ => {}
+    }
+
     let sample1 = input_from("sample-1.txt")?;
     let sample2 = input_from("sample-2.txt")?;
-    println!("Sample part 1: {}", part1(&sample1));
-    println!("Sample part 2: {}", part2(&sample2));
+    println!("Sample part 1: {}", part1(&sample1)?);
+    println!("Sample part 2: {}", part2(&sample2)?);
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    println!("Part 1: {}", part1(&input)?);
+    println!("Part 2: {}", part2(&input)?);
 
     Ok(())
 }
@@ -25,55 +64,498 @@ struct Input {
 }
 
 enum Direction { Left, Right }
-type Network = HashMap<Label, Node>;
 type Label = String;
 
+// nodes are numbered densely in the order they're defined
+type NodeId = u32;
+
 struct Node {
     name: Label,
     left: Label,
     right: Label
 }
 
-fn part1(input: &Input) -> u32 {
-    let mut counter = 1;
-    let mut position: &str = "AAA";
+// the labels are interned once while parsing so walking the network is just array lookups
+// instead of hashing strings at every step. the labels are kept around for display
+struct Network {
+    labels: Vec<Label>,
+    ids: HashMap<Label, NodeId>,
 
-    for direction in input.directions.iter().cycle() {
-        position = match direction {
-            Direction::Left  => &input.network.get(position).unwrap().left,
-            Direction::Right => &input.network.get(position).unwrap().right
-        };
-        
-        if position == "ZZZ" { break }
-        counter += 1;
+    // the [left, right] neighbours of each node
+    next: Vec<[NodeId; 2]>,
+
+    // which nodes have labels ending in Z, for part 2
+    is_end: Vec<bool>
+}
+
+impl Network {
+    fn id(&self, label: &str) -> Option<NodeId> {
+        self.ids.get(label).copied()
+    }
+
+    fn label(&self, id: NodeId) -> &str {
+        &self.labels[id as usize]
+    }
+
+    fn step(&self, id: NodeId, direction: &Direction) -> NodeId {
+        match direction {
+            Direction::Left  => self.next[id as usize][0],
+            Direction::Right => self.next[id as usize][1]
+        }
     }
 
-    counter
+    // the nodes with labels ending in this character
+    fn ids_ending_with(&self, c: char) -> Vec<NodeId> {
+        (0..self.labels.len() as NodeId).filter(|&id| self.label(id).ends_with(c))
+                                        .collect()
+    }
+
+    // look up a node we need for a walk, with a useful error if it isn't there
+    fn require(&self, label: &str) -> Result<NodeId, String> {
+        self.id(label)
+            .ok_or(format!("There is no node {} in the network", label))
+    }
+
+    // every node we can get to from this one by any sequence of lefts and rights
+    fn reachable_from(&self, start: NodeId) -> Vec<bool> {
+        let mut seen = vec![false; self.labels.len()];
+        let mut stack = vec![start];
+        seen[start as usize] = true;
+
+        while let Some(id) = stack.pop() {
+            for &next in &self.next[id as usize] {
+                if !seen[next as usize] {
+                    seen[next as usize] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        seen
+    }
 }
 
-// doesn't finish in time, need to find a shortcut
-fn part2(input: &Input) -> u32 {
-    let mut counter = 1;
-    let mut positions: Vec<&str> =
-        input.network.keys()
-                     .filter(|name| name.ends_with('A'))
-                     .map(|name| name.as_str())
-                     .collect();
+fn part1(input: &Input) -> Result<u64, String> {
+    let network = &input.network;
+    let start = network.require("AAA")?;
+    let goal  = network.require("ZZZ")?;
+
+    if !network.reachable_from(start)[goal as usize] {
+        return Err("ZZZ can't be reached from AAA by any path through the network".into())
+    }
+
+    steps_until(input, start, |id| id == goal)
+}
+
+// simulating every ghost step by step takes far too long, so instead find where each ghost
+// settles into its cycle. once they all have, each ghost is on a Z node at the times that are
+// certain remainders modulo its cycle length, and the chinese remainder theorem combines those
+fn part2(input: &Input) -> Result<u64, String> {
+    let network = &input.network;
+
+    let mut positions: Vec<NodeId> = network.ids_ending_with('A');
+    if positions.is_empty() {
+        return Err("There are no nodes ending in A to start from".into())
+    }
+
+    // if any one ghost can never land on a Z node then they certainly can't all land on one
+    // together, and saying which one is more helpful than the general error below
+    for &start in &positions {
+        steps_until(input, start, |id| network.is_end[id as usize])?;
+    }
+
+    let cycles: Vec<Cycle> = positions.iter().map(|&start| ghost_cycle(input, start)).collect();
+    let settled = cycles.iter().map(|cycle| cycle.offset).max().unwrap() as u64;
 
+    // until the last ghost reaches its cycle, just walk them all
+    let mut counter = 0;
     for direction in input.directions.iter().cycle() {
+        if counter + 1 >= settled { break }
+
         for p in &mut positions {
-            *p = match direction {
-                Direction::Left  => &input.network.get(*p).unwrap().left,
-                Direction::Right => &input.network.get(*p).unwrap().right
+            *p = network.step(*p, direction)
+        }
+        counter += 1;
+
+        if positions.iter().all(|&p| network.is_end[p as usize]) {
+            return Ok(counter)
+        }
+    }
+
+    // the times a ghost is on a Z node, as remainders modulo its cycle length. merge them in one
+    // ghost at a time, keeping the remainders that work for every ghost so far
+    let mut modulus: u64 = 1;
+    let mut remainders: Vec<u64> = vec![0];
+
+    for cycle in &cycles {
+        let length = cycle.steps.len() as u64;
+        let ends: Vec<u64> =
+            cycle.steps.iter()
+                       .enumerate()
+                       .filter(|(_, (id, _))| network.is_end[*id as usize])
+                       .map(|(j, _)| (cycle.offset + j) as u64 % length)
+                       .collect();
+
+        let mut merged = Vec::new();
+        let mut lcm = modulus;
+        for &a in &remainders {
+            for &b in &ends {
+                if let Some((remainder, m)) = crt(a, modulus, b, length)? {
+                    merged.push(remainder);
+                    lcm = m;
+                }
             }
         }
-        if positions.iter().all(|name| name.ends_with('Z')) {
-            break
+
+        if merged.is_empty() {
+            return Err("The ghosts never all land on Z nodes at the same time".into())
         }
+
+        merged.sort();
+        merged.dedup();
+        remainders = merged;
+        modulus = lcm;
+    }
+
+    // the first time at or after they've all settled with one of the remainders
+    let from = settled.max(1);
+    remainders.iter()
+              .map(|&r| from.checked_add((r + modulus - from % modulus) % modulus))
+              .min()
+              .flatten()
+              .ok_or("The number of steps overflows a u64".to_string())
+}
+
+// the t with t = a mod m and t = b mod n, as a remainder modulo lcm(m, n), or None if there's
+// no such t because a and b disagree modulo the gcd
+fn crt(a: u64, m: u64, b: u64, n: u64) -> Result<Option<(u64, u64)>, String> {
+    let g = gcd(m, n);
+    if a % g != b % g { return Ok(None) }
+
+    let lcm = (m / g).checked_mul(n)
+                     .ok_or("The ghosts' combined cycle length overflows a u64".to_string())?;
+
+    // t = a + m*k where m*k = b - a (mod n), so k = (b - a)/g * inverse(m/g) (mod n/g)
+    let (m, n, a, b, g) = (m as i128, n as i128, a as i128, b as i128, g as i128);
+    let k = ((b - a) / g).rem_euclid(n / g) * inverse(m / g, n / g) % (n / g);
+
+    Ok(Some(((a + m * k).rem_euclid(lcm as i128) as u64, lcm)))
+}
+
+// the inverse of a modulo n, for a and n with no common factors
+fn inverse(a: i128, n: i128) -> i128 {
+    let (mut old_r, mut r) = (a.rem_euclid(n), n);
+    let (mut old_s, mut s) = (1i128, 0i128);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+
+    old_s.rem_euclid(n)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// follow the instructions from the start node until we land on a goal node. the walk is fully
+// determined by which node we're on and where we are in the instructions, so if we ever see the
+// same pair twice we're going around in circles and will never arrive
+fn steps_until(input: &Input, start: NodeId, is_goal: impl Fn(NodeId) -> bool) -> Result<u64, String> {
+    let network = &input.network;
+    let length = input.directions.len();
+
+    let mut seen = vec![false; network.labels.len() * length];
+    let mut position = start;
+    let mut counter = 0;
+
+    for (i, direction) in input.directions.iter().enumerate().cycle() {
+        let state = position as usize * length + i;
+        if seen[state] {
+            return Err(format!("Starting from {}, the instructions never lead to a goal node",
+                               network.label(start)))
+        }
+        seen[state] = true;
+
+        position = network.step(position, direction);
         counter += 1;
+
+        if is_goal(position) { break }
+    }
+
+    Ok(counter)
+}
+
+// where a ghost ends up going around in circles. the walk is determined by the node and our
+// position in the instructions, so the cycle is a loop of those pairs. it's entered after
+// `offset` steps and takes `steps.len()` steps to come back around
+struct Cycle {
+    offset: usize,
+    steps: Vec<(NodeId, usize)>
+}
+
+// walk from the start until we see a (node, instruction) pair for the second time
+fn ghost_cycle(input: &Input, start: NodeId) -> Cycle {
+    let network = &input.network;
+    let length = input.directions.len();
+
+    // when each state was first visited
+    let mut visited: Vec<Option<usize>> = vec![None; network.labels.len() * length];
+    let mut path: Vec<(NodeId, usize)> = Vec::new();
+    let mut position = start;
+
+    for (i, direction) in input.directions.iter().enumerate().cycle() {
+        let state = position as usize * length + i;
+        if let Some(offset) = visited[state] {
+            return Cycle { offset, steps: path.split_off(offset) }
+        }
+        visited[state] = Some(path.len());
+        path.push((position, i));
+
+        position = network.step(position, direction);
+    }
+
+    unreachable!("the instructions are never empty")
+}
+
+// colours for each ghost's cycle, reused if there are more ghosts than colours
+const CYCLE_COLOURS: [&str; 6] = ["blue", "darkorange", "purple", "forestgreen", "deeppink", "brown"];
+
+// the network as a graphviz digraph. start nodes are filled green and end nodes red. with
+// `cycles` set, the edges and nodes each ghost loops around forever are drawn in its own colour
+fn to_dot(input: &Input, cycles: bool) -> String {
+    let network = &input.network;
+    let mut out = String::from("digraph network {\n");
+
+    // the colour of each node and each [left, right] edge, if a ghost's cycle uses it
+    let mut node_colour: Vec<Option<&str>> = vec![None; network.labels.len()];
+    let mut edge_colour: Vec<[Option<&str>; 2]> = vec![[None, None]; network.labels.len()];
+
+    if cycles {
+        for (ghost, start) in network.ids_ending_with('A').into_iter().enumerate() {
+            let colour = CYCLE_COLOURS[ghost % CYCLE_COLOURS.len()];
+            let cycle = ghost_cycle(input, start);
+
+            out += &format!("    // {} ({}) loops every {} steps after the first {}\n",
+                            network.label(start), colour, cycle.steps.len(), cycle.offset);
+
+            for (id, i) in cycle.steps {
+                let side = match input.directions[i] { Direction::Left => 0, Direction::Right => 1 };
+                node_colour[id as usize].get_or_insert(colour);
+                edge_colour[id as usize][side].get_or_insert(colour);
+            }
+        }
+    }
+
+    for (id, label) in network.labels.iter().enumerate() {
+        let mut attributes = Vec::new();
+
+        if label.ends_with('A') {
+            attributes.push("style=filled, fillcolor=palegreen".to_string());
+        } else if label.ends_with('Z') {
+            attributes.push("style=filled, fillcolor=lightcoral".to_string());
+        }
+        if let Some(colour) = node_colour[id] {
+            attributes.push(format!("color={}, penwidth=2", colour));
+        }
+
+        // the labels are quoted since an unquoted DOT id can't start with a digit, like 11A
+        if attributes.is_empty() {
+            out += &format!("    \"{}\";\n", label);
+        } else {
+            out += &format!("    \"{}\" [{}];\n", label, attributes.join(", "));
+        }
     }
 
-    counter
+    for (id, label) in network.labels.iter().enumerate() {
+        let [left, right] = network.next[id];
+
+        // draw one edge when both ways go to the same place, unless a cycle only uses one of them
+        let edges = if left == right && edge_colour[id][0] == edge_colour[id][1] {
+            vec![(left, "L/R", edge_colour[id][0])]
+        } else {
+            vec![(left, "L", edge_colour[id][0]), (right, "R", edge_colour[id][1])]
+        };
+
+        for (to, name, colour) in edges {
+            out += &format!("    \"{}\" -> \"{}\" [label=\"{}\"", label, network.label(to), name);
+            if let Some(colour) = colour {
+                out += &format!(", color={}, penwidth=2", colour);
+            }
+            out += "];\n";
+        }
+    }
+
+    out += "}\n";
+    out
+}
+
+
+/* Queries that ignore the instructions */
+
+// the fewest steps from one node to another if we could choose left or right at each node.
+// returns the nodes along the way, including both ends
+fn shortest_path(network: &Network, from: NodeId, to: NodeId) -> Option<Vec<NodeId>> {
+    // breadth-first, remembering which node we first reached each node from
+    let mut came_from: Vec<Option<NodeId>> = vec![None; network.labels.len()];
+    let mut queue = VecDeque::from([from]);
+    came_from[from as usize] = Some(from);
+
+    while let Some(id) = queue.pop_front() {
+        if id == to {
+            let mut path = vec![to];
+            let mut at = to;
+            while at != from {
+                at = came_from[at as usize].unwrap();
+                path.push(at);
+            }
+            path.reverse();
+            return Some(path)
+        }
+
+        for &next in &network.next[id as usize] {
+            if came_from[next as usize].is_none() {
+                came_from[next as usize] = Some(id);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+// eg. "2 steps: AAA -R-> CCC -L-> ZZZ"
+fn describe_path(network: &Network, from: &str, to: &str) -> Result<String, String> {
+    let (from, to) = (network.require(from)?, network.require(to)?);
+
+    let path = shortest_path(network, from, to)
+                   .ok_or(format!("There is no path from {} to {}", network.label(from), network.label(to)))?;
+
+    let mut out = format!("{} steps: {}", path.len() - 1, network.label(from));
+    for pair in path.windows(2) {
+        let turn = match network.next[pair[0] as usize] {
+            [left, right] if left == right => "L/R",
+            [left, _]     if left == pair[1] => "L",
+            _                                => "R"
+        };
+        out += &format!(" -{}-> {}", turn, network.label(pair[1]));
+    }
+
+    Ok(out)
+}
+
+// the labels of the nodes reachable from this one, in sorted order
+fn reachable_labels<'a>(network: &'a Network, from: &str) -> Result<Vec<&'a str>, String> {
+    let seen = network.reachable_from(network.require(from)?);
+
+    let mut labels: Vec<&str> =
+        network.labels.iter()
+                      .zip(seen)
+                      .filter(|(_, seen)| *seen)
+                      .map(|(label, _)| label.as_str())
+                      .collect();
+
+    labels.sort();
+    Ok(labels)
+}
+
+fn describe_reachable(network: &Network, from: &str) -> Result<String, String> {
+    let labels = reachable_labels(network, from)?;
+    Ok(format!("{} of {} nodes are reachable from {}:\n{}\n",
+               labels.len(), network.labels.len(), from, labels.join(" ")))
+}
+
+// tarjan's algorithm. every node ends up in exactly one component, and within a component any
+// node can reach any other. the components come out with each one's successors before it
+fn strongly_connected_components(network: &Network) -> Vec<Vec<NodeId>> {
+    struct Tarjan<'a> {
+        network: &'a Network,
+        index: Vec<Option<usize>>,
+        lowlink: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<NodeId>,
+        next_index: usize,
+        components: Vec<Vec<NodeId>>
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, id: NodeId) {
+            let v = id as usize;
+            self.index[v] = Some(self.next_index);
+            self.lowlink[v] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(id);
+            self.on_stack[v] = true;
+
+            for next in self.network.next[v] {
+                let w = next as usize;
+                match self.index[w] {
+                    None => {
+                        self.visit(next);
+                        self.lowlink[v] = self.lowlink[v].min(self.lowlink[w]);
+                    }
+                    Some(index) if self.on_stack[w] => {
+                        self.lowlink[v] = self.lowlink[v].min(index);
+                    }
+                    _ => {}
+                }
+            }
+
+            // v is the root of a component, which is everything above it on the stack
+            if Some(self.lowlink[v]) == self.index[v] {
+                let mut component = Vec::new();
+                loop {
+                    let w = self.stack.pop().unwrap();
+                    self.on_stack[w as usize] = false;
+                    component.push(w);
+                    if w == id { break }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let count = network.labels.len();
+    let mut tarjan = Tarjan {
+        network,
+        index: vec![None; count],
+        lowlink: vec![0; count],
+        on_stack: vec![false; count],
+        stack: Vec::new(),
+        next_index: 0,
+        components: Vec::new()
+    };
+
+    for id in 0..count as NodeId {
+        if tarjan.index[id as usize].is_none() {
+            tarjan.visit(id);
+        }
+    }
+
+    tarjan.components
+}
+
+// one component per line, biggest first, with the labels in each sorted
+fn describe_components(network: &Network) -> String {
+    let mut components: Vec<Vec<&str>> =
+        strongly_connected_components(network)
+            .into_iter()
+            .map(|component| {
+                let mut labels: Vec<&str> = component.into_iter().map(|id| network.label(id)).collect();
+                labels.sort();
+                labels
+            })
+            .collect();
+
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+    let mut out = format!("{} strongly connected components\n", components.len());
+    for labels in components {
+        out += &format!("{}: {}\n", labels.len(), labels.join(" "));
+    }
+    out
 }
 
 
@@ -123,12 +605,16 @@ impl FromStr for Input {
                           
         Ok(Input {
             directions: parse_instructions(lines[0])?,
-            network: network_from_nodes(nodes)
+            network: network_from_nodes(nodes)?
         })
     }
 }
 
 fn parse_instructions(line: &str) -> Result<Vec<Direction>, String> {
+    if line.is_empty() {
+        return Err("Expected at least one instruction on the first line".into())
+    }
+
     let mut directions: Vec<Direction> = Vec::new();
 
     for char in line.chars() {
@@ -142,12 +628,74 @@ fn parse_instructions(line: &str) -> Result<Vec<Direction>, String> {
     Ok(directions)
 }
 
-fn network_from_nodes(nodes: Vec<Node>) -> Network {
-    let mut network = Network::new();
+// something wrong with the list of nodes that would stop us building a network from it
+enum Problem {
+    Duplicate(Label),
+    Undefined { node: Label, reference: Label }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Problem::Duplicate(name) =>
+                write!(f, "Node {} is defined more than once", name),
+            Problem::Undefined { node, reference } =>
+                write!(f, "Node {} refers to {}, which is never defined", node, reference)
+        }
+    }
+}
+
+// check the whole list up front so we can report everything that's wrong at once
+fn validate_nodes(nodes: &[Node]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut defined: HashMap<&str, usize> = HashMap::new();
+
     for node in nodes {
-        network.insert(node.name.clone(), node);
+        let count = defined.entry(&node.name).or_insert(0);
+        *count += 1;
+
+        // only mention each duplicate once, however many times it's repeated
+        if *count == 2 {
+            problems.push(Problem::Duplicate(node.name.clone()));
+        }
+    }
+
+    for node in nodes {
+        for reference in [&node.left, &node.right] {
+            if !defined.contains_key(reference.as_str()) {
+                problems.push(Problem::Undefined { node     : node.name.clone(),
+                                                   reference: reference.clone() });
+            }
+        }
     }
-    network
+
+    problems
+}
+
+// give each defined node an id, then translate its left and right labels to ids
+fn network_from_nodes(nodes: Vec<Node>) -> Result<Network, String> {
+    let problems = validate_nodes(&nodes);
+    if !problems.is_empty() {
+        return Err(problems.iter()
+                           .map(|problem| problem.to_string())
+                           .collect::<Vec<String>>()
+                           .join("\n"))
+    }
+
+    let ids: HashMap<Label, NodeId> =
+        nodes.iter()
+             .enumerate()
+             .map(|(i, node)| (node.name.clone(), i as NodeId))
+             .collect();
+
+    let next = nodes.iter()
+                    .map(|node| [ids[&node.left], ids[&node.right]])
+                    .collect();
+
+    let labels: Vec<Label> = nodes.into_iter().map(|node| node.name).collect();
+    let is_end = labels.iter().map(|label| label.ends_with('Z')).collect();
+
+    Ok(Network { labels, ids, next, is_end })
 }
 
 // Read input from a file
@@ -169,21 +717,21 @@ mod tests {
     #[test]
     fn test_sample_part1() {
         let input = input_from("sample-1.txt").unwrap();
-        assert_eq!(2, part1(&input));
+        assert_eq!(Ok(2), part1(&input));
         let input = input_from("sample-2.txt").unwrap();
-        assert_eq!(6, part1(&input));
+        assert_eq!(Ok(6), part1(&input));
     }
 
     #[test]
     fn test_sample_part2() {
         let input = input_from("sample-3.txt").unwrap();
-        assert_eq!(6, part2(&input))
+        assert_eq!(Ok(6), part2(&input))
     }
 
     #[test]
     fn test_part1() {
         let input = input_from("input.txt").unwrap();
-        assert_eq!(20569, part1(&input))
+        assert_eq!(Ok(20569), part1(&input))
     }
 
     /*
@@ -194,6 +742,165 @@ mod tests {
     }
     */
 
+    #[test]
+    fn test_interned_network() {
+        let input = input_from("sample-2.txt").unwrap();
+        let network = &input.network;
+
+        assert_eq!(Some(0), network.id("AAA"));
+        assert_eq!(Some(2), network.id("ZZZ"));
+        assert_eq!("BBB", network.label(network.step(0, &Direction::Left)));
+        assert_eq!("ZZZ", network.label(network.step(1, &Direction::Right)));
+
+        let input = input_from("sample-3.txt").unwrap();
+        let starts: Vec<&str> = input.network.ids_ending_with('A').into_iter().map(|id| input.network.label(id)).collect();
+        assert_eq!(vec!["11A", "22A"], starts);
+    }
+
+    #[test]
+    fn test_part2_cycles() {
+        // 11A is on 11Z at odd steps and 22A on 22Z at even ones, so never together
+        let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22B)\n22B = (22Z, 22Z)\n22Z = (22B, 22B)"
+                        .parse::<Input>().unwrap();
+        assert_eq!(Err("The ghosts never all land on Z nodes at the same time".to_string()), part2(&input));
+
+        // cycles of 2 and 3, with the 22A ghost taking 2 steps to settle and on 22Z at 4, 7, 10...
+        let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n\
+                     22A = (22X, 22X)\n22X = (22B, 22B)\n22B = (22C, 22C)\n22C = (22Z, 22Z)\n22Z = (22B, 22B)"
+                        .parse::<Input>().unwrap();
+        assert_eq!(Ok(7), part2(&input));
+
+        // both ghosts land on a Z before they've settled into their cycles
+        let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11B, 11B)\n11B = (11B, 11B)\n\
+                     22A = (22Z, 22Z)\n22Z = (22B, 22B)\n22B = (22C, 22C)\n22C = (22B, 22B)"
+                        .parse::<Input>().unwrap();
+        assert_eq!(Ok(1), part2(&input));
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(Ok(Some((8, 15))), crt(2, 3, 3, 5));
+        assert_eq!(Ok(Some((23, 105))), crt(8, 15, 2, 7));
+        assert_eq!(Ok(None), crt(1, 2, 0, 4));
+        assert_eq!(Ok(Some((3, 12))), crt(3, 4, 3, 6));
+    }
+
+    #[test]
+    fn test_invalid_nodes() {
+        let result = "L\n\nAAA = (BBB, AAA)".parse::<Input>();
+        assert_eq!(Some("Node AAA refers to BBB, which is never defined".to_string()), result.err());
+
+        let result = "L\n\nAAA = (AAA, CCC)\nAAA = (AAA, AAA)\nAAA = (AAA, AAA)".parse::<Input>();
+        assert_eq!(Some("Node AAA is defined more than once\n\
+                         Node AAA refers to CCC, which is never defined".to_string()), result.err());
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        let input = "L\n\nBBB = (AAA, AAA)\nAAA = (AAA, AAA)".parse::<Input>().unwrap();
+        assert_eq!(Err("There is no node ZZZ in the network".to_string()), part1(&input));
+
+        let input = "L\n\nAAA = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)".parse::<Input>().unwrap();
+        assert_eq!(Err("Starting from AAA, the instructions never lead to a goal node".to_string()),
+                   part1(&input));
+
+        let input = "LR\n\nAAA = (AAA, AAA)\nZZZ = (ZZZ, AAA)".parse::<Input>().unwrap();
+        assert_eq!(Err("ZZZ can't be reached from AAA by any path through the network".to_string()),
+                   part1(&input));
+
+        // the 22A ghost never lands on a Z node
+        let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22Z)\n22B = (22A, 22A)\n22Z = (22Z, 22Z)"
+                        .parse::<Input>().unwrap();
+        assert_eq!(Err("Starting from 22A, the instructions never lead to a goal node".to_string()),
+                   part2(&input));
+    }
+
+    #[test]
+    fn test_ghost_cycle() {
+        let input = input_from("sample-3.txt").unwrap();
+
+        // 11A -L-> 11B -R-> 11Z -L-> 11B ...
+        let cycle = ghost_cycle(&input, input.network.id("11A").unwrap());
+        let steps: Vec<(&str, usize)> = cycle.steps.iter().map(|&(id, i)| (input.network.label(id), i)).collect();
+        assert_eq!(1, cycle.offset);
+        assert_eq!(vec![("11B", 1), ("11Z", 0)], steps);
+
+        // 22A -L-> 22B -R-> 22C -L-> 22Z -R-> 22B -L-> 22C -R-> 22Z -L-> 22B ...
+        let cycle = ghost_cycle(&input, input.network.id("22A").unwrap());
+        assert_eq!(1, cycle.offset);
+        assert_eq!(6, cycle.steps.len());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let input = "LR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)".parse::<Input>().unwrap();
+
+        assert_eq!("digraph network {\n\
+                    \x20   \"AAA\" [style=filled, fillcolor=palegreen];\n\
+                    \x20   \"BBB\";\n\
+                    \x20   \"ZZZ\" [style=filled, fillcolor=lightcoral];\n\
+                    \x20   \"AAA\" -> \"BBB\" [label=\"L/R\"];\n\
+                    \x20   \"BBB\" -> \"AAA\" [label=\"L\"];\n\
+                    \x20   \"BBB\" -> \"ZZZ\" [label=\"R\"];\n\
+                    \x20   \"ZZZ\" -> \"ZZZ\" [label=\"L/R\"];\n\
+                    }\n", to_dot(&input, false));
+
+        // the one ghost goes AAA -L-> BBB -R-> ZZZ then stays there
+        assert_eq!("digraph network {\n\
+                    \x20   // AAA (blue) loops every 2 steps after the first 2\n\
+                    \x20   \"AAA\" [style=filled, fillcolor=palegreen];\n\
+                    \x20   \"BBB\";\n\
+                    \x20   \"ZZZ\" [style=filled, fillcolor=lightcoral, color=blue, penwidth=2];\n\
+                    \x20   \"AAA\" -> \"BBB\" [label=\"L/R\"];\n\
+                    \x20   \"BBB\" -> \"AAA\" [label=\"L\"];\n\
+                    \x20   \"BBB\" -> \"ZZZ\" [label=\"R\"];\n\
+                    \x20   \"ZZZ\" -> \"ZZZ\" [label=\"L/R\", color=blue, penwidth=2];\n\
+                    }\n", to_dot(&input, true));
+    }
+
+    #[test]
+    fn test_to_dot_ghosts() {
+        // labels starting with a digit have to be quoted to be valid DOT
+        let input = input_from("sample-3.txt").unwrap();
+        let dot = to_dot(&input, true);
+
+        assert!(dot.contains("    // 11A (blue) loops every 2 steps after the first 1\n"));
+        assert!(dot.contains("    \"11A\" [style=filled, fillcolor=palegreen];\n"));
+        assert!(dot.contains("    \"22Z\" [style=filled, fillcolor=lightcoral, color=darkorange, penwidth=2];\n"));
+        assert!(dot.contains("    \"11B\" -> \"11Z\" [label=\"R\", color=blue, penwidth=2];\n"));
+        assert!(dot.contains("    \"XXX\" -> \"XXX\" [label=\"L/R\"];\n"));
+        assert!(!dot.contains("    11"));
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let input = input_from("sample-2.txt").unwrap();
+        assert_eq!(Ok("1 steps: AAA -L/R-> BBB".to_string()), describe_path(&input.network, "AAA", "BBB"));
+        assert_eq!(Ok("2 steps: AAA -L/R-> BBB -R-> ZZZ".to_string()), describe_path(&input.network, "AAA", "ZZZ"));
+        assert_eq!(Ok("0 steps: BBB".to_string()), describe_path(&input.network, "BBB", "BBB"));
+        assert_eq!(Err("There is no path from ZZZ to AAA".to_string()), describe_path(&input.network, "ZZZ", "AAA"));
+    }
+
+    #[test]
+    fn test_reachable() {
+        let input = input_from("sample-3.txt").unwrap();
+        assert_eq!(Ok(vec!["11A", "11B", "11Z", "XXX"]), reachable_labels(&input.network, "11A"));
+        assert_eq!(Ok(vec!["22B", "22C", "22Z"]), reachable_labels(&input.network, "22Z"));
+        assert!(reachable_labels(&input.network, "AAA").is_err());
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let input = input_from("sample-3.txt").unwrap();
+        assert_eq!("5 strongly connected components\n\
+                    3: 22B 22C 22Z\n\
+                    2: 11B 11Z\n\
+                    1: 11A\n\
+                    1: 22A\n\
+                    1: XXX\n",
+                   describe_components(&input.network));
+    }
+
     #[test]
     fn test_parse_node() {
         let node = "AAA = (BBB, CCC)".parse::<Node>().unwrap();
@@ -2,13 +2,34 @@
 
 fn main() -> Result<(), String> {
 
+    // day09 --steps <k> [file] predicts the value k steps past the end of each history, or
+    // before the start if k is negative
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--steps") {
+        let steps = args.get(2).ok_or("Expected a number of steps after --steps")?;
+        let steps = steps.parse::<i64>().map_err(|_| format!("Invalid number of steps: {}", steps))?;
+        let file = args.get(3).map(String::as_str).unwrap_or("input.txt");
+
+        for history in input_from(file)?.histories {
+            println!("{}", extrapolate(&history.values, steps)?);
+        }
+        return Ok(())
+    }
+
+    // day09 --fit [file] shows the polynomial behind each history
+    if args.get(1).map(String::as_str) == Some("--fit") {
+        let file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+        print!("{}", describe_fits(&input_from(file)?)?);
+        return Ok(())
+    }
+
     let sample = input_from("sample.txt")?;
-    println!("Sample part 1: {}", part1(&sample)); // 114
-    println!("Sample part 2: {}", part2(&sample)); // 2
+    println!("Sample part 1: {}", part1(&sample)?); // 114
+    println!("Sample part 2: {}", part2(&sample)?); // 2
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    println!("Part 1: {}", part1(&input)?);
+    println!("Part 2: {}", part2(&input)?);
 
     Ok(())
 }
@@ -18,58 +39,265 @@ struct Input {
 }
 
 struct History {
-    values: Vec<i32>
+    values: Vec<i64>
 }
 
-// a generator is a function that takes a list of values and a recursively-calculated value
-// and does something with them to generate a new value
-type Generator = fn(&[i32], i32) -> i32;
+// the difference between parts 1/2 is just which way we extrapolate. part 1 predicts the next
+// value after the end of each line and part 2 the one before the start
+fn part1(input: &Input) -> Result<i64, String> {
+    process_histories(&input.histories, 1)
+}
+
+fn part2(input: &Input) -> Result<i64, String> {
+    process_histories(&input.histories, -1)
+}
+
+fn process_histories(histories: &[History], steps: i64) -> Result<i64, String> {
+    histories.iter()
+             .try_fold(0i64, |sum, history| {
+                 sum.checked_add(extrapolate(&history.values, steps)?)
+                    .ok_or("The sum of the extrapolated values overflows an i64".to_string())
+             })
+}
+
+// predict the value `steps` places past the end of the list, or before the start if negative.
+//
+// building the difference triangle and summing back up is the same as evaluating the lowest
+// degree polynomial through the values. newton's backward difference formula writes that
+// polynomial in terms of the last number in each row of the triangle, so k steps past the end
+// of the list is:
+//
+//   sum over rows j of  last(row j) * C(k+j-1, j)
+//
+// the triangle stops at the first row of all zeros, so there's only one term per row that
+// matters and the numbers only get big if the answer does. going backwards is the same as
+// going forwards through the reversed list
+fn extrapolate(values: &[i64], steps: i64) -> Result<i64, String> {
+    if values.len() <= 1 {
+        return Err(format!("Need at least 2 values to extrapolate from, got {}", values.len()))
+    }
+
+    if steps == 0 {
+        return Ok(values[values.len()-1])
+    }
+
+    if steps < 0 {
+        let reversed: Vec<i64> = values.iter().rev().copied().collect();
+        let steps = steps.checked_neg()
+                         .ok_or(format!("Can't extrapolate {} steps backwards", steps))?;
+        return extrapolate(&reversed, steps)
+    }
+
+    let overflow = || format!("Overflow extrapolating {} steps from {:?}", steps, values);
+
+    let k = steps as i128;
+    let mut total: i128 = 0;
+
+    for (j, row) in difference_table(values)?.iter().enumerate() {
+        let last = row[row.len()-1];
+        if last == 0 { continue }
 
-fn part1(input: &Input) -> i32 {
-    // the difference between parts 1/2 is the generator that calculates the new number per line
-    // part 1 adds the recursively-calculated value to the last value in the line
-    fn generate(values: &[i32], rec: i32) -> i32 {
-        rec + values[values.len()-1]
+        let j = j as i128;
+        let term = binomial(k + j - 1, j).and_then(|weight| weight.checked_mul(last))
+                                         .ok_or_else(overflow)?;
+        total = total.checked_add(term).ok_or_else(overflow)?;
     }
 
-    process_histories(&input.histories, generate)
+    i64::try_from(total).map_err(|_| overflow())
 }
 
-fn part2(input: &Input) -> i32 {
-    // part 2 subtracts the recursively-calculated value from the first value in the line
-    fn generate(values: &[i32], rec: i32) -> i32 {
-        values[0] - rec
+// n choose k, or None if it doesn't fit. multiplying before dividing keeps every intermediate
+// result a whole number since it's always a smaller binomial coefficient times something
+fn binomial(n: i128, k: i128) -> Option<i128> {
+    if k < 0 || k > n { return Some(0) }
+
+    let k = k.min(n - k);
+    (0..k).try_fold(1i128, |c, j| c.checked_mul(n - j).map(|c| c / (j + 1)))
+}
+
+
+/* Fitting polynomials */
+
+// the polynomial through a history's values, taking the first value as x = 0. `settles` is
+// false if the difference table ran out of rows before reaching all zeros, in which case the
+// degree is just as high as the number of values allows and we can't be sure it's the real one
+struct Fit {
+    degree: usize,
+    coefficients: Vec<Rational>,    // lowest power first
+    settles: bool
+}
+
+// newton's forward difference formula writes the polynomial as
+//
+//   f(x) = sum over j of  first(row j) * C(x, j)
+//
+// where row j of the difference table is the jth differences. C(x, j) is x(x-1)...(x-j+1) / j!
+// so multiplying those out gives the coefficients of each power of x, which can be fractions
+fn fit(values: &[i64]) -> Result<Fit, String> {
+    let table = difference_table(values)?;
+    let settles = table.last().unwrap().iter().all(|&v| v == 0);
+
+    // an all-zero last row isn't part of the polynomial
+    let rows = if settles { &table[..table.len()-1] } else { &table[..] };
+
+    let overflow = || format!("Overflow fitting a polynomial to {:?}", values);
+
+    let mut coefficients = vec![Rational::from(0); rows.len().max(1)];
+    let mut falling = vec![1i128];     // x(x-1)...(x-j+1), lowest power first
+    let mut factorial = 1i128;
+
+    for (j, row) in rows.iter().enumerate() {
+        if j > 0 {
+            falling = multiply_by_x_minus(&falling, j as i128 - 1).ok_or_else(overflow)?;
+            factorial = factorial.checked_mul(j as i128).ok_or_else(overflow)?;
+        }
+
+        for (power, &c) in falling.iter().enumerate() {
+            let term = c.checked_mul(row[0])
+                        .and_then(|num| Rational::new(num, factorial))
+                        .ok_or_else(overflow)?;
+
+            coefficients[power] = coefficients[power].add(&term).ok_or_else(overflow)?;
+        }
     }
 
-    process_histories(&input.histories, generate)
+    Ok(Fit {
+        degree: rows.len().saturating_sub(1),
+        coefficients,
+        settles
+    })
 }
 
-fn process_histories(histories: &[History], generate: Generator) -> i32 {
-    histories.iter()
-             .map(|history| extrapolate(&history.values, generate))
-             .sum()
+// the rows of differences, starting with the values themselves, until a row is all zeros or
+// only has one number left
+fn difference_table(values: &[i64]) -> Result<Vec<Vec<i128>>, String> {
+    if values.is_empty() {
+        return Err("Can't fit a polynomial to an empty history".into())
+    }
+
+    let mut table = vec![values.iter().map(|&v| v as i128).collect::<Vec<i128>>()];
+
+    loop {
+        let row = table.last().unwrap();
+        if row.len() == 1 || row.iter().all(|&v| v == 0) {
+            return Ok(table)
+        }
+
+        let next = row.windows(2)
+                      .map(|window| window[1].checked_sub(window[0]))
+                      .collect::<Option<Vec<i128>>>()
+                      .ok_or(format!("Overflow taking the differences of {:?}", values))?;
+        table.push(next);
+    }
 }
 
-fn extrapolate(values: &[i32], generate: Generator) -> i32 {
-    if values.len() <= 1 {
-        panic!("Need at least 2 elements in the vector for extrapolate() to work");
+// multiply a polynomial by (x - t)
+fn multiply_by_x_minus(poly: &[i128], t: i128) -> Option<Vec<i128>> {
+    let mut result = vec![0i128; poly.len() + 1];
+    for (power, &c) in poly.iter().enumerate() {
+        result[power + 1] = result[power + 1].checked_add(c)?;
+        result[power] = result[power].checked_sub(c.checked_mul(t)?)?;
     }
-    
-    // the base case, all values in the list are 0
-    if values.iter().all(|v| *v == 0) {
-        return 0
+    Some(result)
+}
+
+// eg. "(1/2)x^2 + (3/2)x + 1"
+fn format_polynomial(coefficients: &[Rational]) -> String {
+    let mut out = String::new();
+
+    for (power, c) in coefficients.iter().enumerate().rev() {
+        if c.num == 0 { continue }
+
+        let sign = if c.num < 0 { "-" } else { "+" };
+        if out.is_empty() {
+            if sign == "-" { out += "-" }
+        } else {
+            out += &format!(" {} ", sign);
+        }
+
+        let size = c.abs();
+        let coefficient = match (power, size.den) {
+            (0, _)                 => size.to_string(),
+            (_, 1) if size.num == 1 => String::new(),
+            (_, 1)                 => size.to_string(),
+            _                      => format!("({})", size)
+        };
+
+        out += &match power {
+            0 => coefficient,
+            1 => format!("{}x", coefficient),
+            _ => format!("{}x^{}", coefficient, power)
+        };
     }
 
-    let diffs = differences(values);
-    let rec = extrapolate(&diffs, generate);    // recursive call
+    if out.is_empty() { "0".to_string() } else { out }
+}
+
+// one line per history with its degree and polynomial
+fn describe_fits(input: &Input) -> Result<String, String> {
+    let mut out = String::new();
+    let mut unsettled = 0;
 
-    generate(values, rec)
+    for history in &input.histories {
+        let fit = fit(&history.values)?;
+        out += &format!("degree {}: {}", fit.degree, format_polynomial(&fit.coefficients));
+
+        if !fit.settles {
+            out += "  (never reaches all zeros)";
+            unsettled += 1;
+        }
+        out += "\n";
+    }
+
+    out += &format!("{} of {} histories never reach all zeros\n", unsettled, input.histories.len());
+    Ok(out)
 }
 
-fn differences(nums: &[i32]) -> Vec<i32> {
-    nums.windows(2)
-        .map(|window| { window[1] - window[0] })
-        .collect()
+// an exact fraction, always in lowest terms with a positive denominator
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rational {
+    num: i128,
+    den: i128
+}
+
+impl Rational {
+    // None if the denominator is zero or something overflows
+    fn new(num: i128, den: i128) -> Option<Self> {
+        if den == 0 { return None }
+
+        let g = gcd(num.unsigned_abs(), den.unsigned_abs()) as i128;
+        let (num, den) = (num / g, den / g);
+
+        if den < 0 { Some(Rational { num: num.checked_neg()?, den: den.checked_neg()? }) }
+        else       { Some(Rational { num, den }) }
+    }
+
+    fn add(&self, other: &Rational) -> Option<Rational> {
+        let num = self.num.checked_mul(other.den)?
+                          .checked_add(other.num.checked_mul(self.den)?)?;
+        Rational::new(num, self.den.checked_mul(other.den)?)
+    }
+
+    fn abs(&self) -> Rational {
+        Rational { num: self.num.abs(), den: self.den }
+    }
+}
+
+impl From<i128> for Rational {
+    fn from(n: i128) -> Self {
+        Rational { num: n, den: 1 }
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.den == 1 { write!(f, "{}", self.num) }
+        else             { write!(f, "{}/{}", self.num, self.den) }
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
 }
 
 
@@ -81,9 +309,9 @@ impl FromStr for History {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let values: Result<Vec<i32>, _> =
+        let values: Result<Vec<i64>, _> =
             s.split_whitespace()
-             .map(|s| s.parse().map_err(|_| "Error parsing i32"))
+             .map(|s| s.parse().map_err(|_| "Error parsing i64"))
              .collect();
         
         Ok(History {
@@ -116,9 +344,66 @@ fn input_from(file: &str) -> Result<Input, String> {
 mod tests {
     use super::*;
 
-    #[test] fn test_sample_part1() { let input = input_from("sample.txt").unwrap(); assert_eq!(114, part1(&input)) }
-    #[test] fn test_sample_part2() { let input = input_from("sample.txt").unwrap(); assert_eq!(2, part2(&input)) }
+    #[test] fn test_sample_part1() { let input = input_from("sample.txt").unwrap(); assert_eq!(Ok(114), part1(&input)) }
+    #[test] fn test_sample_part2() { let input = input_from("sample.txt").unwrap(); assert_eq!(Ok(2), part2(&input)) }
+
+    #[test] fn test_part1() { let input = input_from("input.txt").unwrap(); assert_eq!(Ok(2043183816), part1(&input)) }
+    #[test] fn test_part2() { let input = input_from("input.txt").unwrap(); assert_eq!(Ok(1118), part2(&input)) }
+
+    #[test]
+    fn test_extrapolate_steps() {
+        let values = [10, 13, 16, 21, 30, 45];
+        assert_eq!(Ok(68), extrapolate(&values, 1));
+        assert_eq!(Ok(45), extrapolate(&values, 0));
+        assert_eq!(Ok(5), extrapolate(&values, -1));
 
-    #[test] fn test_part1() { let input = input_from("input.txt").unwrap(); assert_eq!(2043183816, part1(&input)) }
-    #[test] fn test_part2() { let input = input_from("input.txt").unwrap(); assert_eq!(1118, part2(&input)) }
+        // the squares, n^2 for n = 1..4
+        let values = [1, 4, 9, 16];
+        assert_eq!(Ok(100), extrapolate(&values, 6));
+        assert_eq!(Ok(0), extrapolate(&values, -1));
+        assert_eq!(Ok(9), extrapolate(&values, -4));
+    }
+
+    #[test]
+    fn test_fit() {
+        let fit = fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(2, fit.degree);
+        assert!(fit.settles);
+        assert_eq!("(1/2)x^2 + (3/2)x + 1", format_polynomial(&fit.coefficients));
+
+        assert_eq!("x^3 - 2x", format_polynomial(&super::fit(&[0, -1, 4, 21, 56]).unwrap().coefficients));
+        assert_eq!("-3", format_polynomial(&super::fit(&[-3, -3, -3]).unwrap().coefficients));
+        assert_eq!("0", format_polynomial(&super::fit(&[0, 0]).unwrap().coefficients));
+
+        // powers of two never settle, so the best we can do is a cubic through all four
+        let fit = super::fit(&[1, 2, 4, 8]).unwrap();
+        assert_eq!(3, fit.degree);
+        assert!(!fit.settles);
+        assert_eq!("(1/6)x^3 + (5/6)x + 1", format_polynomial(&fit.coefficients));
+    }
+
+    #[test]
+    fn test_describe_fits() {
+        let input = input_from("sample.txt").unwrap();
+        assert_eq!(Ok("degree 1: 3x\n\
+                       degree 2: (1/2)x^2 + (3/2)x + 1\n\
+                       degree 3: (1/3)x^3 - x^2 + (11/3)x + 10\n\
+                       0 of 3 histories never reach all zeros\n".to_string()),
+                   describe_fits(&input));
+    }
+
+    #[test]
+    fn test_extrapolate_errors() {
+        assert_eq!(Err("Need at least 2 values to extrapolate from, got 1".to_string()), extrapolate(&[5], 1));
+        assert!(extrapolate(&[0, i64::MAX], 1).is_err());
+        assert!(extrapolate(&[1, 2, 4, 8, 16, 32, 64], 1_000_000).is_err());
+
+        // a history as long as the real ones but only linear can go a long way without overflowing
+        let values: Vec<i64> = (0..21).map(|x| 3 * x).collect();
+        assert_eq!(Ok(3060), extrapolate(&values, 1000));
+        assert_eq!(Ok(3 * (20 + 1_000_000_000_000_000)), extrapolate(&values, 1_000_000_000_000_000));
+        assert_eq!(Ok(-3000), extrapolate(&values, -1000));
+        assert_eq!(Err("Can't extrapolate -9223372036854775808 steps backwards".to_string()),
+                   extrapolate(&[1, 2], i64::MIN));
+    }
 }
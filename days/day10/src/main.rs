@@ -1,15 +1,37 @@
 // https://adventofcode.com/2023/day/10
 
-use std::ops::Index;
+use std::str::FromStr;
 
 fn main() -> Result<(), String> {
 
+    // day10 --render [--interior] [--out <file>] [input file] draws the maze instead of solving
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "--render").is_some() {
+        let options = RenderOptions::from_args(args)?;
+        let input = input_from(&options.file)?;
+
+        // colours are only for the terminal, the file is plain text so it can be diffed
+        match &options.out {
+            Some(out) => std::fs::write(out, render(&input, options.interior, false))
+                             .map_err(|err| format!("Failed to write file: {}", err))?,
+            None      => print!("{}", render(&input, options.interior, true))
+        }
+        return Ok(())
+    }
+
+    // day10 --loops [input file] lists every closed loop of pipes in the maze
+    if args.next_if(|arg| arg == "--loops").is_some() {
+        let file = args.next().unwrap_or("input.txt".to_string());
+        print!("{}", describe_loops(&analyse(&input_from(&file)?)));
+        return Ok(())
+    }
+
     let sample = input_from("sample-1.txt")?;
-    println!("Sample part 1: {}", part1(&sample)); // 8
+    println!("Sample part 1: {}", part1(&sample)?); // 8
     println!("Sample part 2: {}", part2(&sample)); // 4
 
     let input = input_from("input.txt")?;
-    println!("Part 1: {}", part1(&input));
+    println!("Part 1: {}", part1(&input)?);
     println!("Part 2: {}", part2(&input));
 
     Ok(())
@@ -26,78 +48,77 @@ struct Grid<T> {
 }
 
 impl<T> Grid<T> {
-    // assume all rows are the same length
-    fn width(&self)  -> usize { self.elements[0].len() }
-    fn height(&self) -> usize { self.elements.len() }
+    // the element at this position, or None if it's off the grid
+    fn get(&self, p: &Position) -> Option<&T> {
+        self.elements.get(p.row)?.get(p.col)
+    }
 
-    // get the available directions from this position, considering only the grid boundaries and not the
-    // underlying pipes (the <T> on Grid is generic right now so we don't yet have chars to inspect)
-    fn directions_from(&self, position: &Position) -> Vec<Direction> {
-        let mut directions = Vec::new();
+    // the position one step in this direction, or None if that would take us off the grid
+    fn neighbour(&self, p: &Position, direction: &Direction) -> Option<Position> {
+        use Direction::*;
 
-        if position.row > 0                 { directions.push(Direction::Up) }
-        if position.row < self.height() - 1 { directions.push(Direction::Down) }
-        if position.col > 0                 { directions.push(Direction::Left) }
-        if position.col < self.width() - 1  { directions.push(Direction::Right) }
+        let next = match direction {
+            Down  => Position { row: p.row + 1,           col: p.col               },
+            Up    => Position { row: p.row.checked_sub(1)?, col: p.col             },
+            Right => Position { row: p.row,               col: p.col + 1           },
+            Left  => Position { row: p.row,               col: p.col.checked_sub(1)? },
+        };
 
-        directions
+        self.get(&next).map(|_| next)
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 struct Position {
     row: usize,
     col: usize
 }
 
-// make Grid indexable with a Position
-impl<T> Index<&Position> for Grid<T> {
-    type Output = T;
-
-    fn index(&self, pos: &Position) -> &Self::Output {
-        if pos.row >= self.elements.len()    { panic!("Index outside of row bounds") }
-        if pos.col >= self.elements[0].len() { panic!("Index outside of col bounds") }
-        
-        &self.elements[pos.row][pos.col]
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "row {}, col {}", self.row + 1, self.col + 1)
     }
 }
 
 impl Grid<char>
 {
-    // which of the possible directions from this position are valid ones, ie the pipe
-    // in that direction is lined up properly for us to get to it from this position's pipe
+    // which directions from this position are valid ones, ie this position's pipe opens that way
+    // and the pipe in that direction opens back towards us. nothing is valid from off the grid
     fn get_valid_directions(&self, p: &Position) -> Vec<Direction> {
-        let this_char = self[p];
+        let this_char = match self.get(p) {
+            Some(&c) => c,
+            None     => return Vec::new()
+        };
 
-        self.directions_from(p)
-            .into_iter()
+        openings(this_char)
+            .iter()
             .filter(|dir| {
-                let next_char = self[ &position_in_direction(p, dir) ];
-
-                // contains: [T] -> T -> bool
-                match dir {
-                    Direction::Right => "J-7".contains(next_char) && "SL-F".contains(this_char),
-                    Direction::Down  => "L|J".contains(next_char) && "SF|7".contains(this_char),
-                    Direction::Left  => "F-L".contains(next_char) && "SJ-7".contains(this_char),
-                    Direction::Up    => "F|7".contains(next_char) && "SL|J".contains(this_char),
-                }
+                self.neighbour(p, dir)
+                    .and_then(|next| self.get(&next).copied())
+                    .is_some_and(|next_char| openings(next_char).contains(&dir.opposite()))
             })
+            .copied()
             .collect()
     }
 }
 
-fn position_in_direction(p: &Position, direction: &Direction) -> Position {
+// the directions a pipe opens in. an S could be any pipe so it opens every way
+fn openings(pipe: char) -> &'static [Direction] {
     use Direction::*;
 
-    match direction {
-        Down  => Position { row: p.row + 1, col: p.col     },
-        Up    => Position { row: p.row - 1, col: p.col     },
-        Right => Position { row: p.row    , col: p.col + 1 },
-        Left  => Position { row: p.row    , col: p.col - 1 },
+    match pipe {
+        '|' => &[Down, Up],
+        '-' => &[Right, Left],
+        'L' => &[Right, Up],
+        'J' => &[Left, Up],
+        '7' => &[Down, Left],
+        'F' => &[Right, Down],
+        'S' => &[Right, Down, Left, Up],
+         _  => &[]
     }
 }
 
-#[derive(PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 enum Direction {
     Right,
     Down,
@@ -105,38 +126,160 @@ enum Direction {
     Up
 }
 
-// count half the steps to get all the way around the loop of pipes. chatgpt 4.0 helped make this slick
-fn part1(input: &Input) -> u32 {
+impl Direction {
+    fn opposite(&self) -> Direction {
+        use Direction::*;
+
+        match self {
+            Right => Left,
+            Down  => Up,
+            Left  => Right,
+            Up    => Down
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Direction::Right => "right",
+            Direction::Down  => "down",
+            Direction::Left  => "left",
+            Direction::Up    => "up"
+        }
+    }
+}
+
+// count half the steps to get all the way around the loop of pipes
+fn part1(input: &Input) -> Result<u32, String> {
+    Ok(loop_positions(input)?.len() as u32 / 2)
+}
+
+// the positions on the loop in the order we walk them, starting at the S.
+// chatgpt 4.0 helped make this slick
+fn loop_positions(input: &Input) -> Result<Vec<Position>, String> {
     
-    // use a position cursor to traverse the pipe in one direction, counting each step
+    // use a position cursor to traverse the pipe in one direction, remembering each step
     let mut position = input.s_position.clone();
     let mut last_position = position.clone();
-    let mut count_steps = 0;
+    let mut positions = Vec::new();
 
     loop {
-        // see where we can go from the current position
+        // see where we can go from the current position. the S was replaced with a pipe that
+        // has two connections, so if we end up anywhere with fewer the pipes lead to a dead end
         let directions = input.grid.get_valid_directions(&position);
         if directions.len() != 2 {
-            panic!("The problem description said there would be 2 valid pipe directions if we're on the pipe")
+            return Err(format!("The pipe from the S dead-ends at {}", position))
         }
 
         // take the first pipe that isn't the one we arrived from (to make sure we don't go backwards)
-        let new_direction: Direction =
-            directions.into_iter()
-                      .find(|dir| last_position != position_in_direction(&position, dir))
-                      .unwrap();
+        let new_position: Position =
+            directions.iter()
+                      .filter_map(|dir| input.grid.neighbour(&position, dir))
+                      .find(|next| *next != last_position)
+                      .ok_or(format!("The pipe turns back on itself at {}", position))?;
                    
         // Update last_position to the current position and move to the new position
-        std::mem::swap(&mut last_position, &mut position);
-        position = position_in_direction(&last_position, &new_direction);
-
-        count_steps += 1;
+        positions.push(position.clone());
+        last_position = std::mem::replace(&mut position, new_position);
 
         // break out of the loop when we arrive back at the starting position
         if position == input.s_position { break }
     }
 
-    count_steps / 2
+    Ok(positions)
+}
+
+
+/* Loops */
+
+// what part a tile plays in the maze. a pipe is on a closed loop if it and every pipe it's
+// connected to has exactly two connections, otherwise it's part of a chain that dead-ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tile {
+    Ground,
+    MainLoop,
+    OtherLoop,
+    DeadEnd
+}
+
+// a closed loop of pipes, identified by its top-left-most tile
+struct Loop {
+    start: Position,
+    length: usize,
+    main: bool
+}
+
+struct Analysis {
+    tiles: Grid<Tile>,
+    loops: Vec<Loop>
+}
+
+// flood out from each pipe we haven't seen yet along its valid connections to find the group of
+// pipes it's connected to, then classify the whole group at once
+fn analyse(input: &Input) -> Analysis {
+    let grid = &input.grid;
+    let mut tiles = Grid {
+        elements: grid.elements.iter().map(|row| vec![Tile::Ground; row.len()]).collect()
+    };
+    let mut seen: Vec<Vec<bool>> = grid.elements.iter().map(|row| vec![false; row.len()]).collect();
+    let mut loops = Vec::new();
+
+    for (r, row) in grid.elements.iter().enumerate() {
+    for (c, &pipe) in row.iter().enumerate() {
+        if seen[r][c] || openings(pipe).is_empty() { continue }
+
+        let start = Position { row: r, col: c };
+        let mut group = vec![start.clone()];
+        let mut stack = vec![start.clone()];
+        seen[r][c] = true;
+
+        while let Some(p) = stack.pop() {
+            for dir in grid.get_valid_directions(&p) {
+                if let Some(next) = grid.neighbour(&p, &dir) {
+                    if !seen[next.row][next.col] {
+                        seen[next.row][next.col] = true;
+                        group.push(next.clone());
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        let closed = group.iter().all(|p| grid.get_valid_directions(p).len() == 2);
+        let main = closed && group.contains(&input.s_position);
+
+        let tile = if main        { Tile::MainLoop  }
+                   else if closed { Tile::OtherLoop }
+                   else           { Tile::DeadEnd   };
+
+        for p in &group {
+            tiles.elements[p.row][p.col] = tile;
+        }
+
+        if closed {
+            loops.push(Loop { start, length: group.len(), main });
+        }
+    }}
+
+    Analysis { tiles, loops }
+}
+
+// eg. "Loop at row 3, col 1: 16 tiles (main loop)"
+fn describe_loops(analysis: &Analysis) -> String {
+    let mut out = String::new();
+
+    for l in &analysis.loops {
+        out += &format!("Loop at {}: {} tiles{}\n",
+                        l.start, l.length, if l.main { " (main loop)" } else { "" });
+    }
+
+    let count = |tile: Tile| analysis.tiles.elements.iter().flatten().filter(|&&t| t == tile).count();
+
+    if !analysis.loops.iter().any(|l| l.main) {
+        out += "The S isn't on a closed loop\n";
+    }
+    out += &format!("{} loops, {} pipes in other loops, {} dead-end pipes\n",
+                    analysis.loops.len(), count(Tile::OtherLoop), count(Tile::DeadEnd));
+    out
 }
 
 // count the number of cells in the interior of the loop
@@ -145,14 +288,131 @@ fn part2(input: &Input) -> u32 {
 }
 
 
+/* Rendering */
+
+struct RenderOptions {
+    interior: bool,
+    out: Option<String>,
+    file: String
+}
+
+impl RenderOptions {
+    fn from_args(mut args: impl Iterator<Item=String>) -> Result<Self, String> {
+        let mut options = RenderOptions {
+            interior: false,
+            out: None,
+            file: "input.txt".to_string()
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--interior" => options.interior = true,
+                "--out"      => options.out = Some(args.next().ok_or("Expected a file name after --out")?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown argument: {}", arg)),
+                _            => options.file = arg
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+const LOOP    : &str = "\x1b[1;36m";    // bold cyan
+const START   : &str = "\x1b[1;33m";    // bold yellow
+const JUNK    : &str = "\x1b[2m";       // dim
+const INTERIOR: &str = "\x1b[34m";      // blue
+const RESET   : &str = "\x1b[0m";
+
+fn box_drawing(pipe: char) -> char {
+    match pipe {
+        '|' => '│',
+        '-' => '─',
+        'L' => '└',
+        'J' => '┘',
+        '7' => '┐',
+        'F' => '┌',
+         c  => c
+    }
+}
+
+// which tiles are on the main loop, and which of the others are inside it
+fn classify_tiles(input: &Input) -> (Vec<Vec<bool>>, Vec<Vec<bool>>) {
+    let grid = &input.grid;
+    let on_loop: Vec<Vec<bool>> =
+        analyse(input).tiles
+                      .elements
+                      .iter()
+                      .map(|row| row.iter().map(|&tile| tile == Tile::MainLoop).collect())
+                      .collect();
+
+    // scanning along a row we cross into or out of the loop at every loop pipe that goes up.
+    // a - doesn't cross, and of the corners only one of L...7 or F...J is counted, which is right
+    // since L...7 crosses the loop and L...J doesn't
+    let mut inside: Vec<Vec<bool>> = on_loop.iter().map(|row| vec![false; row.len()]).collect();
+    for (r, row) in grid.elements.iter().enumerate() {
+        let mut crossings = 0;
+        for (c, &pipe) in row.iter().enumerate() {
+            if on_loop[r][c] {
+                if "|LJ".contains(pipe) { crossings += 1 }
+            } else {
+                inside[r][c] = crossings % 2 == 1;
+            }
+        }
+    }
+
+    (on_loop, inside)
+}
+
+// draw the maze with box-drawing characters. the S keeps its letter so it's easy to find. with
+// `colour` the loop is highlighted and the junk pipes dimmed with ANSI escapes, and with
+// `interior` the tiles enclosed by the loop are shaded
+fn render(input: &Input, interior: bool, colour: bool) -> String {
+    let (on_loop, inside) = classify_tiles(input);
+    let mut out = String::new();
+
+    for (r, row) in input.grid.elements.iter().enumerate() {
+        for (c, &pipe) in row.iter().enumerate() {
+            let (tile, style) =
+                if input.s_position == (Position { row: r, col: c }) { ('S', START) }
+                else if on_loop[r][c]                              { (box_drawing(pipe), LOOP) }
+                else if interior && inside[r][c]                   { ('░', INTERIOR) }
+                else if pipe == '.'                                { (' ', "") }
+                else                                               { (box_drawing(pipe), JUNK) };
+
+            if colour && !style.is_empty() {
+                out += &format!("{}{}{}", style, tile, RESET);
+            } else {
+                out.push(tile);
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+
 /* Parsing */
 
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        input_from_str(input)
+    }
+}
+
 // Read input from a file
 fn input_from(file: &str) -> Result<Input, String> {
 
     let input = std::fs::read_to_string(file)
         .map_err(|err| format!("Failed to read file: {}", err))?;
 
+    input.parse()
+}
+
+fn input_from_str(input: &str) -> Result<Input, String> {
+
     // mutable because we'll replace the S with the proper pipe segment before wrapping up the parse
     let mut grid = Grid {
         elements: input.lines()
@@ -178,8 +438,14 @@ fn replace_s_in_grid(grid: &mut Grid<char>) -> Result<Position, String> {
                          .ok_or("Couldn't find the S".to_string())?;
     let directions = grid.get_valid_directions(&s_position);
     
-    if directions.len() != 2 {
-        return Err("Expected two valid directions from the S".to_string())
+    match directions.len() {
+        0 => return Err(format!("The S at {} doesn't connect to any pipes", s_position)),
+        1 => return Err(format!("The S at {} only connects to one pipe, going {}",
+                                s_position, directions[0].name())),
+        2 => {}
+        n => return Err(format!("The S at {} connects to {} pipes going {}, so we can't tell which two make the loop",
+                                s_position, n,
+                                directions.iter().map(Direction::name).collect::<Vec<&str>>().join(", ")))
     }
 
     // figure out the shape of pipe that connects these two directions and replace the S with it
@@ -239,7 +505,7 @@ mod tests {
     #[test]
     fn test_sample_part1() {
         let input = input_from("sample-1.txt").unwrap();
-        assert_eq!(8, part1(&input))
+        assert_eq!(Ok(8), part1(&input))
     }
 
     #[test]
@@ -251,7 +517,84 @@ mod tests {
     #[test]
     fn test_part1() {
         let input = input_from("input.txt").unwrap();
-        assert_eq!(6828, part1(&input))
+        assert_eq!(Ok(6828), part1(&input))
+    }
+
+    #[test]
+    fn test_valid_directions() {
+        let input = input_from("sample-1.txt").unwrap();
+        let grid = &input.grid;
+
+        assert_eq!(vec![Direction::Right, Direction::Down], grid.get_valid_directions(&Position { row: 2, col: 0 }));
+        assert_eq!(vec![Direction::Down, Direction::Left], grid.get_valid_directions(&Position { row: 0, col: 3 }));
+
+        // ground, and off the edge
+        assert!(grid.get_valid_directions(&Position { row: 0, col: 0 }).is_empty());
+        assert!(grid.get_valid_directions(&Position { row: 9, col: 9 }).is_empty());
+        assert_eq!(None, grid.neighbour(&Position { row: 0, col: 0 }, &Direction::Up));
+    }
+
+    #[test]
+    fn test_bad_s() {
+        assert_eq!(Some("The S at row 1, col 2 doesn't connect to any pipes".to_string()),
+                   ".S.\n.-.".parse::<Input>().err());
+        assert_eq!(Some("The S at row 1, col 2 only connects to one pipe, going down".to_string()),
+                   ".S.\n.|.".parse::<Input>().err());
+        assert_eq!(Some("The S at row 2, col 2 connects to 4 pipes going right, down, left, up, \
+                         so we can't tell which two make the loop".to_string()),
+                   ".|.\n-S-\n.|.".parse::<Input>().err());
+    }
+
+    #[test]
+    fn test_analyse_loops() {
+        // the main loop on the left, another loop on the right, and a dead-end pipe in the middle
+        let input = "F7.F7\n\
+                     SJ-LJ\n\
+                     ..L-7".parse::<Input>().unwrap();
+        let analysis = analyse(&input);
+
+        assert_eq!(vec![Tile::MainLoop, Tile::MainLoop, Tile::Ground, Tile::OtherLoop, Tile::OtherLoop],
+                   analysis.tiles.elements[0]);
+        assert_eq!(Tile::DeadEnd, analysis.tiles.elements[1][2]);
+        assert_eq!(Tile::DeadEnd, analysis.tiles.elements[2][4]);
+
+        assert_eq!("Loop at row 1, col 1: 4 tiles (main loop)\n\
+                    Loop at row 1, col 4: 4 tiles\n\
+                    2 loops, 4 pipes in other loops, 4 dead-end pipes\n", describe_loops(&analysis));
+
+        // the S is connected to two pipes but they don't go anywhere
+        let input = "S-\n|.".parse::<Input>().unwrap();
+        assert_eq!(Err("The pipe from the S dead-ends at row 1, col 2".to_string()), part1(&input));
+        assert_eq!("The S isn't on a closed loop\n\
+                    0 loops, 0 pipes in other loops, 3 dead-end pipes\n", describe_loops(&analyse(&input)));
+    }
+
+    #[test]
+    fn test_render() {
+        let input = input_from("sample-1.txt").unwrap();
+        assert_eq!("  ┌┐ \n\
+                    \x20┌┘│ \n\
+                    S┘ └┐\n\
+                    │┌──┘\n\
+                    └┘   \n", render(&input, false, false));
+
+        let input = input_from("sample-2.txt").unwrap();
+        assert_eq!("           \n\
+                    \x20S───────┐ \n\
+                    \x20│┌─────┐│ \n\
+                    \x20││     ││ \n\
+                    \x20││     ││ \n\
+                    \x20│└─┐ ┌─┘│ \n\
+                    \x20│░░│ │░░│ \n\
+                    \x20└──┘ └──┘ \n\
+                    \x20          \n", render(&input, true, false));
+    }
+
+    #[test]
+    fn test_render_colour() {
+        let input = "F7-\nSJ.".parse::<Input>().unwrap();
+        assert_eq!("\x1b[1;36m┌\x1b[0m\x1b[1;36m┐\x1b[0m\x1b[2m─\x1b[0m\n\
+                    \x1b[1;33mS\x1b[0m\x1b[1;36m┘\x1b[0m \n", render(&input, false, true));
     }
 
     #[test]
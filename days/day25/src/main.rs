@@ -0,0 +1,282 @@
+// https://adventofcode.com/2023/day/25
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+fn main() -> Result<(), String> {
+
+    let sample = input_from("sample.txt")?;
+    let cut = cut_wires(&sample.graph)?;
+    println!("Sample part 1: {}", part1(&cut)); // 54
+    println!("Sample cut: {}", describe_cut(&sample.graph, &cut));
+
+    let input = input_from("input.txt")?;
+    let cut = cut_wires(&input.graph)?;
+    println!("Part 1: {}", part1(&cut));
+    println!("Cut: {}", describe_cut(&input.graph, &cut));
+
+    Ok(())
+}
+
+struct Input {
+    graph: Graph
+}
+
+// an undirected graph of components. the labels are interned to dense indices once during the
+// parse so the rest of the code can work with plain usizes
+struct Graph {
+    labels: Vec<String>,
+    edges: Vec<(usize, usize)>
+}
+
+// the result of splitting the graph in two: how many components ended up on each side, and
+// which edges we had to cut to get there
+struct Cut {
+    sizes: (usize, usize),
+    edges: Vec<(usize, usize)>
+}
+
+// the puzzle says there are exactly three wires to disconnect
+const WIRES_TO_CUT: usize = 3;
+
+// karger's algorithm is randomized, so give up eventually instead of spinning forever on an input
+// that doesn't have a cut of the size we're looking for
+const MAX_TRIALS: u64 = 10_000;
+
+// the search is the slow part, so main does it once and both answers come from the same cut
+fn cut_wires(graph: &Graph) -> Result<Cut, String> {
+    find_cut(graph, WIRES_TO_CUT)
+        .ok_or(format!("Couldn't find a cut of {} wires in {} trials", WIRES_TO_CUT, MAX_TRIALS))
+}
+
+fn part1(cut: &Cut) -> usize {
+    cut.sizes.0 * cut.sizes.1
+}
+
+// list the cut wires by name, eg "bvb/cmg, hfx/pzl, jqt/nvd"
+fn describe_cut(graph: &Graph, cut: &Cut) -> String {
+    graph.edge_labels(&cut.edges)
+         .iter()
+         .map(|(a, b)| format!("{}/{}", a, b))
+         .collect::<Vec<String>>()
+         .join(", ")
+}
+
+// run karger's min-cut repeatedly, seeding each trial with its trial number so the runs are
+// reproducible, until one of them happens to cut exactly the number of edges we want
+fn find_cut(graph: &Graph, size: usize) -> Option<Cut> {
+    (1..=MAX_TRIALS).map(|seed| karger(graph, seed))
+                    .find(|cut| cut.edges.len() == size)
+}
+
+// one trial of karger's algorithm. contracting edges in a random order until two super-nodes are
+// left is the same as adding edges to a union-find in a random order until two sets are left
+fn karger(graph: &Graph, seed: u64) -> Cut {
+    let mut rng = XorShift::new(seed);
+    let mut sets = DisjointSets::new(graph.labels.len());
+    let mut remaining = graph.labels.len();
+
+    // fisher-yates shuffle of the edge order
+    let mut order: Vec<usize> = (0..graph.edges.len()).collect();
+    for i in (1..order.len()).rev() {
+        let j = rng.below(i + 1);
+        order.swap(i, j);
+    }
+
+    for i in order {
+        if remaining <= 2 { break }
+
+        let (a, b) = graph.edges[i];
+        if sets.union(a, b) {
+            remaining -= 1;
+        }
+    }
+
+    // every edge that still joins two different super-nodes is part of the cut
+    let edges: Vec<(usize, usize)> =
+        graph.edges.iter()
+                   .filter(|&&(a, b)| sets.find(a) != sets.find(b))
+                   .copied()
+                   .collect();
+
+    let left = sets.find(0);
+    let left_size = sets.size_of(left);
+
+    Cut {
+        sizes: (left_size, graph.labels.len() - left_size),
+        edges
+    }
+}
+
+impl Graph {
+    // the labels at either end of each edge, with each pair and the list as a whole sorted so
+    // the output doesn't depend on which trial found the cut
+    fn edge_labels(&self, edges: &[(usize, usize)]) -> Vec<(&str, &str)> {
+        let mut labels: Vec<(&str, &str)> =
+            edges.iter()
+                 .map(|&(a, b)| {
+                     let (a, b) = (self.labels[a].as_str(), self.labels[b].as_str());
+                     if a < b { (a, b) } else { (b, a) }
+                 })
+                 .collect();
+
+        labels.sort();
+        labels
+    }
+}
+
+// a union-find over node indices, tracking the size of each set at its root
+struct DisjointSets {
+    parent: Vec<usize>,
+    size: Vec<usize>
+}
+
+impl DisjointSets {
+    fn new(count: usize) -> Self {
+        DisjointSets {
+            parent: (0..count).collect(),
+            size: vec![1; count]
+        }
+    }
+
+    // path halving keeps the trees shallow without needing recursion
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    // join the sets containing a and b, returning false if they were already the same set
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b { return false }
+
+        let (big, small) = if self.size[a] >= self.size[b] { (a, b) } else { (b, a) };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        true
+    }
+
+    fn size_of(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+}
+
+// a tiny xorshift generator so the trials are deterministic without pulling in a rand crate
+struct XorShift {
+    state: u64
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero, and small seeds start out poorly mixed, so scramble it first
+        XorShift { state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // a number in 0..n. the modulo bias is irrelevant for our purposes
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+
+/* Parsing */
+
+impl FromStr for Input {
+    type Err = String;
+
+    // jqt: rhn xhk nvd
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        let mut labels: Vec<String> = Vec::new();
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+
+        for line in s.lines() {
+            let (from, tos) = line.split_once(':')
+                                  .ok_or(format!("Expected a colon in line: {}", line))?;
+
+            let from = intern(from.trim(), &mut indices, &mut labels);
+
+            for to in tos.split_whitespace() {
+                let to = intern(to, &mut indices, &mut labels);
+                edges.push((from, to));
+            }
+        }
+
+        if labels.len() < 2 {
+            return Err("Expected at least two components in the input".into())
+        }
+
+        Ok(Input {
+            graph: Graph { labels, edges }
+        })
+    }
+}
+
+// look up a label's index, assigning the next one if we haven't seen it yet
+fn intern<'a>(label  : &'a str,
+              indices: &mut HashMap<&'a str, usize>,
+              labels : &mut Vec<String>) -> usize
+{
+    *indices.entry(label).or_insert_with(|| {
+        labels.push(label.to_string());
+        labels.len() - 1
+    })
+}
+
+// Read input from a file
+fn input_from(file: &str) -> Result<Input, String> {
+
+    let input = std::fs::read_to_string(file)
+        .map_err(|err| format!("Failed to read file: {}", err))?;
+
+    input.parse()
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_part1() {
+        let input = input_from("sample.txt").unwrap();
+        assert_eq!(54, part1(&cut_wires(&input.graph).unwrap()))
+    }
+
+    #[test]
+    fn test_sample_cut_edges() {
+        let input = input_from("sample.txt").unwrap();
+        let cut = find_cut(&input.graph, 3).unwrap();
+
+        assert_eq!(vec![("bvb", "cmg"), ("hfx", "pzl"), ("jqt", "nvd")],
+                   input.graph.edge_labels(&cut.edges));
+    }
+
+    #[test]
+    fn test_no_cut() {
+        // a triangle can be split by cutting two wires but never exactly three
+        let input = "a: b c\nb: c".parse::<Input>().unwrap();
+        assert_eq!(Some("Couldn't find a cut of 3 wires in 10000 trials".to_string()),
+                   cut_wires(&input.graph).err());
+    }
+
+    #[test]
+    fn test_parse_edges() {
+        let input = "jqt: rhn xhk\nrhn: xhk".parse::<Input>().unwrap();
+        assert_eq!(vec!["jqt", "rhn", "xhk"], input.graph.labels);
+        assert_eq!(vec![(0, 1), (0, 2), (1, 2)], input.graph.edges);
+    }
+}